@@ -1,15 +1,14 @@
-mod languages;
-mod utils;
-
 use kolmogorov::*;
 use languages::*;
-use utils::*;
+
+use std::io::Write;
 
 fn main() -> std::io::Result<()> {
     let lang = Polynomials;
     let oeis = oeis::load_oeis_def()?;
 
     let mut output_file = std::fs::File::create("data/3_rec_poly")?;
+    let mut records_file = std::fs::File::create("data/3_rec_poly.jsonl")?;
 
     println!("{} sequences:", oeis.seq.len());
 
@@ -30,18 +29,21 @@ fn main() -> std::io::Result<()> {
                 iterations: 50_000,
                 ..Default::default()
             },
-            Options { print_freq: None },
-        );
-
-        if output.score.is_none() {
-            use std::io::*;
+            Options {
+                print_freq: None,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
-            let term = output.term;
-            let analysis = output.analysis;
+        let record = output.record(format!("A{:06}", id));
+        record.write_to(&mut records_file)?;
+        records_file.flush()?;
 
+        if output.score.is_none() {
             let text = format!(
                 "Solution found for A{:06}: {} (≈ {})",
-                id, term, analysis
+                id, output.term, output.analysis
             );
 
             println!("{}", text);
@@ -1,11 +1,7 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
-mod utils;
-use utils::oeis::*;
-
 fn main() {
     let lang = NumLogic::new(2);
     let ty = ty!(Var => Bool);
@@ -28,22 +24,38 @@ fn main() {
         .collect::<Vec<_>>();
     seqs.sort_by_key(|(_id, sq)| sq.clone());
 
+    let ctxt = lang.context();
+
+    // Programs are evaluated on the search threads, matched against OEIS here
     let programs = (1..)
         .inspect(|n| println!("Searching size: {}", n))
-        .flat_map(|n| search(&lang, vec![], &ty, n));
+        .flat_map(|n| {
+            par_search_filter_map(
+                &lang,
+                vec![],
+                &ty,
+                n,
+                available_threads(),
+                &Budget::default(),
+                |program, analysis| {
+                    let mut outs = vec![];
+                    for num in 0..limit as u32 {
+                        let prog = term!([program] [:num]);
 
-    for (program, analysis) in programs {
-        let mut outs = vec![];
-        for num in 0..limit as u32 {
-            let prog = term!([program] [:num]);
+                        // Programs which fail to evaluate match no sequence
+                        let out = ctxt.evaluate(&prog).and_then(|t| t.get::<bool>()).ok()?;
 
-            let out = lang.context().evaluate(&prog).get::<bool>();
+                        if out {
+                            outs.push(num);
+                        }
+                    }
 
-            if out {
-                outs.push(num);
-            }
-        }
+                    Some((program.share(), analysis.clone(), outs))
+                },
+            )
+        });
 
+    for (program, analysis, outs) in programs {
         let search = seqs.binary_search_by_key(&&outs, |(_id, sq)| sq);
         if search.is_ok() {
             println!("{} ≈ {}:", program, analysis);
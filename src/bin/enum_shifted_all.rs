@@ -1,11 +1,7 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
-mod utils;
-use utils::oeis::*;
-
 fn main() {
     let lang = NumLogic::new(2);
     let ty = ty!(Var => Num);
@@ -32,12 +28,14 @@ fn main() {
         .inspect(|n| println!("Searching size: {}", n))
         .flat_map(|n| search(&lang, vec![], &ty, n));
 
-    for (program, analysis) in programs {
+    'next: for (program, analysis) in programs {
         let mut outs = vec![];
         for num in 1..=limit as u32 {
             let prog = term!([program] [:num]);
 
-            let out = lang.context().evaluate(&prog).get::<u32>();
+            let Ok(out) = lang.context().evaluate(&prog).and_then(|t| t.get::<u32>()) else {
+                continue 'next;
+            };
 
             outs.push(out);
         }
@@ -1,11 +1,7 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
-mod utils;
-use utils::oeis::*;
-
 fn main() {
     let lang = NumLogic::new(2);
     let ty = ty!(Var => Num);
@@ -34,11 +30,9 @@ fn main() {
         for i in 1..limit {
             let prog = term!([program] [:i]);
 
-            let out_prog = lang.context().evaluate(&prog);
-
-            let out = out_prog.get::<u32>();
+            let out = lang.context().evaluate(&prog).and_then(|t| t.get::<u32>());
 
-            if out != nums[i as usize - 1] {
+            if out != Ok(nums[i as usize - 1]) {
                 continue 'next;
             }
         }
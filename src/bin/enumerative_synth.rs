@@ -1,12 +1,10 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
-mod utils;
-use utils::oeis::*;
+use std::fmt::Display;
 
-fn main() {
+fn main() -> std::io::Result<()> {
     let lang = NumLogic::new(2);
     let ty = ty!(Var => Bool);
 
@@ -28,21 +26,34 @@ fn main() {
 
     let start = std::time::Instant::now();
 
+    let mut terms = 0;
+    let mut found = None;
+
     'next: for (program, analysis) in programs {
+        terms += 1;
+
         for num in 0..limit {
             let prog = term!([program] [:num]);
 
-            let out = lang.context().evaluate(&prog).get::<bool>();
+            let out = lang.context().evaluate(&prog).and_then(|t| t.get::<bool>());
 
-            if out != nums.contains(&num) {
+            if out != Ok(nums.contains(&num)) {
                 continue 'next;
             }
         }
         println!("Found: {} ~= {}", program, analysis);
+        found = Some((program, analysis));
         break;
     }
 
     let end = std::time::Instant::now();
+    let time = end.duration_since(start).as_secs_f64();
+
+    println!("Total time: {}", time as f32);
 
-    println!("Total time: {}", end.duration_since(start).as_secs_f32());
+    let found = found
+        .as_ref()
+        .map(|(program, analysis)| (program, analysis as &dyn Display));
+    let record = RunRecord::enumerative(format!("A{:06}", key), found, terms, time, None);
+    record.write_to(std::fs::File::create("data/enumerative_synth.jsonl")?)
 }
@@ -1,16 +1,20 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
 fn main() {
     let lang = NumLogic::new(2);
 
-
     let term = term!(f -> exists f (b -> exists f (k -> and (prime (atom b)) (bool (eq (atom f) (pow b k))))));
 
     println!("Size: {}", term.size());
 
+    // Ill-typed programs would only fail part way through evaluation
+    if let Err(err) = check(&lang.context(), &vec![], &term, &ty!(Var => Num)) {
+        eprintln!("Ill-typed program: {}", err);
+        std::process::exit(1);
+    }
+
     let nps = [
         0, 1, 2, 2, 3, 3, 4, 4, 4, 4, 5, 5, 6, 6, 6, 6, 7, 7, 8, 8, 8, 8, 9, 9, 9, 9, 9, 9, 10, 10,
         11, 11, 11, 11, 11, 11, 12, 12, 12, 12, 13, 13, 14, 14, 14, 14, 15, 15, 15, 15, 15, 15, 16,
@@ -20,8 +24,8 @@ fn main() {
 
     for i in 2u32..70 {
         let term = term!([term] [:i]);
-        let out = lang.context().evaluate(&term);
-        let np = out.get::<u32>();
+        let out = lang.context().evaluate(&term).unwrap();
+        let np = out.get::<u32>().unwrap();
 
         assert_eq!(np, nps[i as usize - 1], "{} -> {}", i, np);
     }
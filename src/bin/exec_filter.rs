@@ -1,11 +1,7 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
-mod utils;
-use utils::*;
-
 fn main() {
     let lang = NumLogic::new(2);
 
@@ -13,6 +9,12 @@ fn main() {
 
     dbg!(term.size());
 
+    // Ill-typed programs would only fail part way through evaluation
+    if let Err(err) = check(&lang.context(), &vec![], &term, &ty!(Var => Bool)) {
+        eprintln!("Ill-typed program: {}", err);
+        std::process::exit(1);
+    }
+
     let oeis = load_oeis(&OEISLoadOptions {
         required: vec!["nonn"],
         ..Default::default()
@@ -27,8 +29,8 @@ fn main() {
 
     for i in 0u32..=limit {
         let term = term!([term] [:i]);
-        let out = lang.context().evaluate(&term);
-        let np = out.get::<bool>();
+        let out = lang.context().evaluate(&term).unwrap();
+        let np = out.get::<bool>().unwrap();
 
         assert_eq!(np, nps.contains(&i), "{} -> {}", i, np);
     }
@@ -1,6 +1,5 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
 fn main() {
@@ -19,7 +18,8 @@ fn main() {
             ..Default::default()
         },
         Options::default(),
-    );
+    )
+    .unwrap();
 
     output.display()
 }
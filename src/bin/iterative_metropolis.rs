@@ -1,12 +1,10 @@
 use kolmogorov::*;
 
-mod languages;
-mod utils;
 use languages::*;
 
 fn main() {
     let lang = Polynomials;
-    let oeis = utils::oeis::load_oeis_def().unwrap();
+    let oeis = oeis::load_oeis_def().unwrap();
     let nums = &oeis.seq[&142];
 
     let examples = nums[1..]
@@ -26,8 +24,12 @@ fn main() {
             iterations: 75_000,
             ..Default::default()
         },
-        Options { print_freq: None },
-    );
+        Options {
+            print_freq: None,
+            ..Default::default()
+        },
+    )
+    .unwrap();
 
     if output.score.is_none() {
         let term = output.term;
@@ -1,19 +0,0 @@
-#![allow(unused_imports)]
-
-mod dumb_num;
-mod cond_poly;
-mod empty;
-mod fib_lang;
-mod fol;
-mod num_logic;
-mod opaque;
-mod polynomials;
-
-pub use dumb_num::*;
-pub use cond_poly::*;
-pub use empty::*;
-pub use fib_lang::*;
-pub use fol::*;
-pub use num_logic::*;
-pub use opaque::*;
-pub use polynomials::*;
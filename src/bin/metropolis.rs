@@ -1,6 +1,5 @@
 use kolmogorov::{metro::metropolis, *};
 
-mod languages;
 use languages::*;
 
 fn main() {
@@ -22,11 +21,9 @@ fn main() {
                 [t] [:x]
             };
 
-            let evaled = lang_ctxt.evaluate(&program);
+            let output = lang_ctxt.evaluate(&program).and_then(|t| t.get::<i32>());
 
-            let output = evaled.get::<i32>();
-
-            if output != y {
+            if output != Ok(y) {
                 num_correct -= 1.;
             }
         }
@@ -45,7 +42,7 @@ fn main() {
     let iterations = 50_000;
 
     let (_, metropolis_search, _) =
-        metropolis(&lang, &start, &ty, scorer, iterations, Options::default());
+        metropolis(&lang, &start, &ty, scorer, iterations, Options::default()).unwrap();
 
     println!("Best Found: {}", metropolis_search);
 }
@@ -1,9 +1,8 @@
 use statrs::distribution::{Continuous, Normal};
-use std::rc::Rc;
+use std::sync::Arc;
 
 use kolmogorov::{metro::metropolis, *};
 
-mod languages;
 use languages::*;
 
 fn main() {
@@ -17,14 +16,14 @@ fn main() {
 
     let mut exec_ctxt = lang_ctxt.clone();
 
-    let fibs: Rc<Vec<i32>> = Rc::new((0..num_examples).map(fib).collect());
+    let fibs: Arc<Vec<i32>> = Arc::new((0..num_examples).map(fib).collect());
     let prevs: Vec<(Identifier, BuiltIn)> = (0..num_examples)
         .map(|n| {
             let fibs2 = fibs.clone();
             let def = builtin! {
                 N => N
                 |c| => {
-                    let c = c.get::<i32>();
+                    let c = c.get::<i32>()?;
                     if 0 < c && c < n {
                         Term::val(fibs2[c as usize])
                     } else {
@@ -32,8 +31,7 @@ fn main() {
                     }
                 }
             };
-            let name: Identifier =
-                Identifier::Name(format!("prevs_{}", n).leak());
+            let name = Identifier::from(format!("prevs_{}", n));
             (name, def)
         })
         .collect();
@@ -61,11 +59,9 @@ fn main() {
                 [t] [Var(rec_arg)] [:n]
             };
 
-            let evaled = exec_ctxt.evaluate(&program);
+            let output = exec_ctxt.evaluate(&program).and_then(|t| t.get::<i32>());
 
-            let output = evaled.get::<i32>();
-
-            if output == f_n {
+            if output == Ok(f_n) {
                 num_correct += 1;
             }
         }
@@ -93,7 +89,7 @@ fn main() {
     let iterations = 150_000;
 
     let (_, metropolis_search, analysis) =
-        metropolis(&lang, &start, &ty, scorer, iterations, Options::default());
+        metropolis(&lang, &start, &ty, scorer, iterations, Options::default()).unwrap();
 
     println!("Best Found: {}", &metropolis_search);
     println!("Semantics:  {}", analysis);
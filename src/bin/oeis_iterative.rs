@@ -1,15 +1,18 @@
-mod languages;
-mod utils;
-
 use kolmogorov::*;
 use languages::*;
-use utils::*;
+
+use std::io::Write;
+use std::time::Duration;
+
+// Give up on a sequence after this long, if set
+const TIMEOUT: Option<Duration> = None;
 
 fn main() -> std::io::Result<()> {
     let lang = Polynomials;
     let oeis = oeis::load_oeis_def()?;
 
     let mut output_file = std::fs::File::create("data/oeis_iterative")?;
+    let mut records_file = std::fs::File::create("data/oeis_iterative.jsonl")?;
 
     println!("{} sequences:", oeis.seq.len());
 
@@ -25,6 +28,11 @@ fn main() -> std::io::Result<()> {
             .enumerate()
             .map(|(i, n)| ((i + 1) as i32, n));
 
+        let mut budget = Budget::default();
+        if let Some(timeout) = TIMEOUT {
+            budget = budget.with_timeout(timeout);
+        }
+
         let output = iterative(
             lang,
             nums[0],
@@ -36,23 +44,29 @@ fn main() -> std::io::Result<()> {
                 iterations: 75_000,
                 ..Default::default()
             },
-            Options { print_freq: None },
-        );
-
-        if output.score.is_none() {
-            use std::io::*;
+            Options {
+                print_freq: None,
+                budget,
+                ..Default::default()
+            },
+        )
+        .unwrap();
 
-            let term = output.term;
-            let analysis = output.analysis;
+        let record = output.record(format!("A{:06}", id));
+        record.write_to(&mut records_file)?;
+        records_file.flush()?;
 
+        if output.score.is_none() {
             let text = format!(
                 "Solution found for A{:06}: {} (≈ {})",
-                id, term, analysis
+                id, output.term, output.analysis
             );
 
             println!("{}", text);
             writeln!(output_file, "{}", text)?;
             output_file.flush()?;
+        } else if let Some(reason) = output.stopped {
+            println!("Gave up on A{:06}: {}", id, reason);
         }
     }
 
@@ -1,6 +1,5 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
 fn main() {
@@ -1,23 +1,35 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
+use std::time::Duration;
+
 type Lang = NumLogic;
 
+// Stop after this long, if set
+const TIMEOUT: Option<Duration> = None;
+
 fn main() {
     let lang = Lang::new(2);
     let ty = ty!(Var => Bool);
 
-    let mut cache = search::Cache::<Lang>::new();
+    let mut budget = Budget::default();
+    if let Some(timeout) = TIMEOUT {
+        budget = budget.with_timeout(timeout);
+    }
 
     for n in 1.. {
         let start = std::time::Instant::now();
 
-        let mut searcher = search::search(&lang, vec![], &ty, n);
+        let mut searcher = search::search(&lang, vec![], &ty, n).with_budget(budget.clone());
 
         let count = searcher.by_ref().count();
 
+        if let Some(reason) = budget.stop_reason() {
+            println!("Stopped during size {}: {}", n, reason);
+            break;
+        }
+
         println!(
             "There are {:>6} known-distinct programs of type {} and size {}.",
             count, ty, n
@@ -1,6 +1,5 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
 use std::time::Instant;
@@ -18,33 +17,45 @@ fn main() {
     let example = term!(p n -> mult p (plus one one));
     println!("Example (|t| = {}): {}\n", example.size(), example);
 
-    let mut total_time = 0f32;
+    let start = Instant::now();
+
+    // Cancelled by the first match, so that the search stops there
+    let budget = Budget::default();
+    let cancel = budget.cancel_token();
 
     for size in 1.. {
-        println!("Time: {}", total_time);
+        println!("Time: {}", start.elapsed().as_secs_f32());
         println!("Searching size {}:", size);
-        'search: for (term, _) in search(&lang, vec![], &targ, size) {
-            for n in 1..5 {
-                let prev = pow(n - 1);
-                let expected = pow(n);
-
-                let program = term! {
-                    [term] [:prev] [:n]
-                };
 
-                let start = Instant::now();
-                let output = ctxt.evaluate(&program);
-                let end = Instant::now();
+        let found = par_search_filter_map(
+            &lang,
+            vec![],
+            &targ,
+            size,
+            available_threads(),
+            &budget,
+            |term, _| {
+                for n in 1..5 {
+                    let prev = pow(n - 1);
+                    let expected = pow(n);
 
-                total_time += end.duration_since(start).as_secs_f32();
+                    let program = term! {
+                        [term] [:prev] [:n]
+                    };
 
-                let output = output.get::<i32>();
+                    let output = ctxt.evaluate(&program).and_then(|t| t.get::<i32>());
 
-                if output != expected {
-                    continue 'search;
+                    if output != Ok(expected) {
+                        return None;
+                    }
                 }
-            }
 
+                cancel.cancel();
+                Some(term.share())
+            },
+        );
+
+        if let Some(term) = found.first() {
             println!("Term Found!");
             println!("{}", term);
             return;
@@ -1,6 +1,5 @@
 use kolmogorov::*;
 
-mod languages;
 use languages::*;
 
 fn main() {
@@ -18,7 +17,8 @@ fn main() {
             ..Default::default()
         },
         Options::default(),
-    );
+    )
+    .unwrap();
 
     output.display()
 }
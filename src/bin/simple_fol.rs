@@ -1,9 +1,7 @@
-mod languages;
-mod utils;
-
 use kolmogorov::*;
 use languages::*;
-use utils::*;
+
+use std::io::Write;
 
 fn main() -> std::io::Result<()> {
     let lang = LogicLang::new(1);
@@ -16,6 +14,7 @@ fn main() -> std::io::Result<()> {
     let oeis = load_oeis(&opts)?;
 
     let mut output_file = std::fs::File::create("data/oeis_individual")?;
+    let mut records_file = std::fs::File::create("data/oeis_individual.jsonl")?;
 
     println!("{} sequences:", oeis.seq.len());
 
@@ -44,18 +43,21 @@ fn main() -> std::io::Result<()> {
             },
             Options {
                 print_freq: Some(1),
+                ..Default::default()
             },
-        );
+        )
+        .unwrap();
 
-        if output.score.is_none() {
-            use std::io::*;
-
-            let term = output.term;
-            let semantics = output.analysis.canon();
+        let record = output.record(format!("A{:06}", id));
+        record.write_to(&mut records_file)?;
+        records_file.flush()?;
 
+        if output.score.is_none() {
             let text = format!(
                 "Solution found for A{:06}: {} (≈ {})",
-                id, term, semantics
+                id,
+                output.term,
+                output.analysis.canon()
             );
 
             println!("{}", text);
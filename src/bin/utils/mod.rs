@@ -1,5 +0,0 @@
-#![allow(unused_imports)]
-
-pub mod oeis;
-
-pub use oeis::*;
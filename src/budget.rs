@@ -0,0 +1,157 @@
+// Limits on the time & work a search or chain may spend. A `Budget` is a handle:
+// clones share their usage, so everything given (a clone of) the same budget
+// draws on the same pool, & all of them stop once any limit is reached.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    max_iterations: Option<usize>, // Metropolis iterations
+    max_terms: Option<usize>,      // Terms yielded by enumeration
+    max_steps: Option<usize>,      // Reduction steps spent scoring candidates
+    cancel: CancelToken,
+    usage: Arc<Usage>,
+}
+
+// Lets another thread (e.g. a batch driver) stop everything drawing on a budget
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    Deadline,
+    Iterations,
+    Terms,
+    Steps,
+    Cancelled,
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    iterations: AtomicUsize,
+    terms: AtomicUsize,
+    steps: AtomicUsize,
+    // The first limit reached (after which the budget stays exhausted)
+    stopped: OnceLock<StopReason>,
+}
+
+impl Budget {
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    // Deadline relative to now
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.with_deadline(Instant::now() + timeout)
+    }
+
+    pub fn with_max_iterations(mut self, iterations: usize) -> Self {
+        self.max_iterations = Some(iterations);
+        self
+    }
+
+    pub fn with_max_terms(mut self, terms: usize) -> Self {
+        self.max_terms = Some(terms);
+        self
+    }
+
+    pub fn with_max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    // The reason to stop, if any limit has been reached (which is then recorded)
+    pub fn check(&self) -> Option<StopReason> {
+        if let Some(reason) = self.usage.stopped.get() {
+            return Some(*reason);
+        }
+
+        let reached = |limit: Option<usize>, used: &AtomicUsize| {
+            limit.is_some_and(|limit| used.load(Ordering::Relaxed) >= limit)
+        };
+
+        let reason = if self.cancel.is_cancelled() {
+            StopReason::Cancelled
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            StopReason::Deadline
+        } else if reached(self.max_iterations, &self.usage.iterations) {
+            StopReason::Iterations
+        } else if reached(self.max_terms, &self.usage.terms) {
+            StopReason::Terms
+        } else if reached(self.max_steps, &self.usage.steps) {
+            StopReason::Steps
+        } else {
+            return None;
+        };
+
+        Some(*self.usage.stopped.get_or_init(|| reason))
+    }
+
+    // The limit which stopped a search or chain, if any
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.usage.stopped.get().copied()
+    }
+
+    pub fn charge_iteration(&self) {
+        self.usage.iterations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn charge_term(&self) {
+        self.usage.terms.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn charge_steps(&self, steps: usize) {
+        self.usage.steps.fetch_add(steps, Ordering::Relaxed);
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.usage.iterations.load(Ordering::Relaxed)
+    }
+
+    pub fn terms(&self) -> usize {
+        self.usage.terms.load(Ordering::Relaxed)
+    }
+
+    pub fn steps(&self) -> usize {
+        self.usage.steps.load(Ordering::Relaxed)
+    }
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use StopReason::*;
+        let text = match self {
+            Deadline => "deadline passed",
+            Iterations => "out of iterations",
+            Terms => "out of terms",
+            Steps => "out of evaluation steps",
+            Cancelled => "cancelled",
+        };
+        write!(f, "{}", text)
+    }
+}
@@ -1,162 +1,529 @@
+use super::schedule::Thermostat;
 use super::*;
 
-use rustc_hash::FxHashMap as HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use statrs::distribution::Discrete;
+use std::fmt::{Display, Formatter};
+use std::ops::ControlFlow;
 
-// Probability of replacing a variable with another
-const REPLACE_VAR: f64 = 0.5;
-// Probability of replacing a small (non-variable) subterm with another of equal size
-const REPLACE_SMALL: f64 = 0.40;
-// Probability of replacing a larger subterm with anohter, potentially of different size
-// This is much more computationally expensive and can erase a lot of progress, but also
-// allows us to exit local minima (we must calculate g(x'|x) & g(x|x'), involving a census
-// of terms we don't even use, so it should be much more unlikely than the others).
-#[allow(unused)]
-const REPLACE_LARGE: f64 = 1. - REPLACE_VAR - REPLACE_SMALL;
-
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Options {
     pub print_freq: Option<usize>, // How often we print out progress, if at all
+    pub seed: Option<u64>,         // Seed for the chain's RNG (from entropy if None)
+    pub mutation: MutationPolicy,  // Which proposals are made, & how often
+    pub schedule: Schedule,        // Temperature of the chain over time
+    pub patience: Option<usize>,   // Stop after this many iterations without a new best
+    pub budget: Budget,            // Limits shared with anything else given this budget
 }
 
 impl Default for Options {
     fn default() -> Self {
         Self {
             print_freq: Some(100),
+            seed: None,
+            mutation: MutationPolicy::default(),
+            schedule: Schedule::default(),
+            patience: None,
+            budget: Budget::default(),
         }
     }
 }
 
-// If F returns None, we stop immediately
+// Why a chain could not be run
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetropolisError {
+    Type(TypeError), // The start term is not of the given type
+    Policy(PolicyError),
+    Schedule(ScheduleError),
+    NoReplicas,          // Replica exchange was given no temperatures
+    NoStart(StopReason), // The budget ran out before a default start term was found
+}
+
+pub type MetropolisResult<T> = Result<T, MetropolisError>;
+
+impl Options {
+    // The same seed, language, start term & scorer always yield the same chain
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+// If F returns None, we stop immediately. Fails if `start` is not of type `ty`,
+// or if `options.mutation` or `options.schedule` is invalid.
 pub fn metropolis<F: FnMut(&Term) -> Option<f64>, L: Language>(
+    lang: &L,
+    start: &Term,
+    ty: &Type,
+    scorer: F,
+    iterations: usize,
+    options: Options,
+) -> MetropolisResult<(usize, Term, Analysis<L>)> {
+    let (i, term, _, analysis) =
+        metropolis_observed(lang, start, ty, scorer, iterations, options, &mut ())?;
+    Ok((i, term, analysis))
+}
+
+// As `metropolis`, reporting each iteration to `observer`. Also returns the score
+// of the term found (None if the scorer stopped us)
+pub fn metropolis_observed<F, L, O>(
     lang: &L,
     start: &Term,
     ty: &Type,
     mut scorer: F,
     iterations: usize,
     options: Options,
-) -> (usize, Term, Analysis<L>) {
-    let mut i = 0;
-    let mut candidate = start.clone();
+    observer: &mut O,
+) -> MetropolisResult<(usize, Term, Option<f64>, Analysis<L>)>
+where
+    F: FnMut(&Term) -> Option<f64>,
+    L: Language,
+    O: Observer,
+{
+    options.mutation.validate()?;
+    options.schedule.validate()?;
 
-    let mut best_candidate = start.clone();
-    let mut best_score = 0.;
-    // Technically, we should probably perform some analysis here.
-    let mut best_analysis = Analysis::Unique;
+    let mut i = 0;
+    let mut stale = 0; // Iterations since a new best was found
+    let mut thermostat = Thermostat::new(options.schedule);
 
-    let Some(mut score) = scorer(start) else {
-        return (i, candidate, best_analysis);
+    let Some(score) = scorer(start) else {
+        return Ok((i, start.clone(), None, Analysis::Unique));
     };
 
-    let mut cache = SizeCache::default();
+    let mut best = Best::new(start, score);
+
+    let mut chain = Chain::new(start, score, options.rng());
 
-    while i < iterations {
+    // Exact unless the language deduplicates terms (beyond the small size)
+    let mut counter = Counter::approximate(lang, options.mutation.small_size::<L>());
+
+    while i < iterations && options.budget.check().is_none() {
         i += 1;
+        options.budget.charge_iteration();
 
         if let Some(freq) = options.print_freq {
             if i % freq == 0 {
                 println!(
-                    "Metropolis progress: {}/{}. Size {}",
+                    "Metropolis progress: {}/{}. Size {}. Temperature {:.3}",
                     i,
                     iterations,
-                    candidate.size()
+                    chain.candidate.size(),
+                    thermostat.temperature()
+                );
+            }
+        }
+
+        let step = chain.step(
+            lang,
+            ty,
+            &options.mutation,
+            &mut counter,
+            &mut scorer,
+            thermostat.temperature(),
+            &mut best,
+            observer,
+        )?;
+
+        match step {
+            ControlFlow::Continue(improved) => {
+                thermostat.step(improved);
+                stale = if improved { 0 } else { stale + 1 };
+            }
+            ControlFlow::Break((term, analysis)) => return Ok((i, term, None, analysis)),
+        }
+
+        if options.patience.is_some_and(|p| stale >= p) {
+            break;
+        }
+    }
+
+    Ok((i, best.term, Some(best.score), best.analysis))
+}
+
+// Replicas of a chain, at fixed temperatures, which periodically exchange candidates
+#[derive(Clone, Debug)]
+pub struct Tempering {
+    pub temperatures: Vec<f64>, // One replica per temperature, coldest first
+    pub swap_freq: usize,       // Iterations between rounds of exchanges
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReplicaStats {
+    pub temperature: f64,
+    pub proposed: usize,       // Mutations proposed (excluding failed mutations)
+    pub accepted: usize,       // Mutations accepted
+    pub swaps_proposed: usize, // Exchanges proposed with the next (hotter) replica
+    pub swaps_accepted: usize,
+}
+
+pub struct TemperingOutput<L: Language> {
+    pub iterations: usize, // Per replica
+    pub term: Term,
+    pub score: Option<f64>, // None if the search was stopped by the scorer
+    pub analysis: Analysis<L>,
+    pub replicas: Vec<ReplicaStats>,
+}
+
+impl Tempering {
+    // `replicas` temperatures, spaced geometrically between 1 & `hottest`
+    pub fn geometric(replicas: usize, hottest: f64, swap_freq: usize) -> Self {
+        let temperatures = (0..replicas)
+            .map(|k| hottest.powf(k as f64 / replicas.saturating_sub(1).max(1) as f64))
+            .collect();
+
+        Self {
+            temperatures,
+            swap_freq,
+        }
+    }
+}
+
+// Parallel tempering, with the replicas' steps interleaved on this thread (as they
+// share the scorer & the counter used to propose terms). Runs one chain per
+// temperature, proposing exchanges between neighbouring replicas every `swap_freq`
+// iterations. `options.schedule` is ignored, `options.seed` seeds all replicas & `options.patience`
+// applies to the best score across all replicas.
+// If F returns None, we stop immediately. Fails if `start` is not of type `ty`, if
+// `options.mutation` or any temperature is invalid, or if there are no replicas.
+pub fn replica_exchange<F: FnMut(&Term) -> Option<f64>, L: Language>(
+    lang: &L,
+    start: &Term,
+    ty: &Type,
+    mut scorer: F,
+    iterations: usize,
+    tempering: &Tempering,
+    options: Options,
+) -> MetropolisResult<TemperingOutput<L>> {
+    options.mutation.validate()?;
+
+    if tempering.temperatures.is_empty() {
+        return Err(MetropolisError::NoReplicas);
+    }
+
+    for &t in &tempering.temperatures {
+        Schedule::Constant(t).validate()?;
+    }
+
+    let mut replicas: Vec<_> = tempering
+        .temperatures
+        .iter()
+        .map(|&temperature| ReplicaStats {
+            temperature,
+            ..Default::default()
+        })
+        .collect();
+
+    let mut i = 0;
+    let mut stale = 0;
+
+    let Some(score) = scorer(start) else {
+        return Ok(TemperingOutput {
+            iterations: i,
+            term: start.clone(),
+            score: None,
+            analysis: Analysis::Unique,
+            replicas,
+        });
+    };
+
+    let mut best = Best::new(start, score);
+
+    let mut rng = options.rng();
+    let mut chains: Vec<_> = replicas
+        .iter()
+        .map(|_| Chain::new(start, score, StdRng::seed_from_u64(rng.gen())))
+        .collect();
+
+    let mut counter = Counter::approximate(lang, options.mutation.small_size::<L>());
+
+    // Each round (of one step per replica) counts as a single iteration of the budget
+    while i < iterations && options.budget.check().is_none() {
+        i += 1;
+        options.budget.charge_iteration();
+
+        if let Some(freq) = options.print_freq {
+            if i % freq == 0 {
+                println!(
+                    "Tempering progress: {}/{}. Best score {}",
+                    i, iterations, best.score
                 );
             }
         }
 
+        let mut improved = false;
+        for (chain, stats) in chains.iter_mut().zip(replicas.iter_mut()) {
+            let step = chain.step(
+                lang,
+                ty,
+                &options.mutation,
+                &mut counter,
+                &mut scorer,
+                stats.temperature,
+                &mut best,
+                &mut (),
+            )?;
+
+            match step {
+                ControlFlow::Continue(chain_improved) => improved |= chain_improved,
+                ControlFlow::Break((term, analysis)) => {
+                    record_stats(&chains, &mut replicas);
+                    return Ok(TemperingOutput {
+                        iterations: i,
+                        term,
+                        score: None,
+                        analysis,
+                        replicas,
+                    });
+                }
+            }
+        }
+
+        stale = if improved { 0 } else { stale + 1 };
+
+        if tempering.swap_freq > 0 && i % tempering.swap_freq == 0 {
+            exchange(&mut chains, &mut replicas, &mut rng);
+        }
+
+        if options.patience.is_some_and(|p| stale >= p) {
+            break;
+        }
+    }
+
+    record_stats(&chains, &mut replicas);
+
+    Ok(TemperingOutput {
+        iterations: i,
+        term: best.term,
+        score: Some(best.score),
+        analysis: best.analysis,
+        replicas,
+    })
+}
+
+// Proposes exchanging the candidates of each pair of neighbouring replicas
+fn exchange<R: Rng + ?Sized>(chains: &mut [Chain], replicas: &mut [ReplicaStats], rng: &mut R) {
+    for k in 1..chains.len() {
+        let (cold, hot) = chains.split_at_mut(k);
+        let (cold, hot) = (&mut cold[k - 1], &mut hot[0]);
+
+        let beta_cold = 1. / replicas[k - 1].temperature;
+        let beta_hot = 1. / replicas[k].temperature;
+
+        // Ratio of the joint targets after & before exchanging candidates
+        let acceptance_prob = (hot.score / cold.score).powf(beta_cold - beta_hot);
+
+        replicas[k - 1].swaps_proposed += 1;
+        if with_probability(rng, acceptance_prob) {
+            replicas[k - 1].swaps_accepted += 1;
+            std::mem::swap(&mut cold.candidate, &mut hot.candidate);
+            std::mem::swap(&mut cold.score, &mut hot.score);
+        }
+    }
+}
+
+fn record_stats(chains: &[Chain], replicas: &mut [ReplicaStats]) {
+    for (chain, stats) in chains.iter().zip(replicas) {
+        stats.proposed = chain.proposed;
+        stats.accepted = chain.accepted;
+    }
+}
+
+// Best proposal seen by any chain
+struct Best<L: Language> {
+    term: Term,
+    score: f64,
+    // Technically, we should probably perform some analysis here.
+    analysis: Analysis<L>,
+}
+
+impl<L: Language> Best<L> {
+    fn new(start: &Term, score: f64) -> Self {
+        Self {
+            term: start.clone(),
+            score,
+            analysis: Analysis::Unique,
+        }
+    }
+}
+
+struct Chain {
+    candidate: Term,
+    score: f64,
+    rng: StdRng,
+    steps: usize,
+    proposed: usize,
+    accepted: usize,
+}
+
+impl Chain {
+    fn new(start: &Term, score: f64, rng: StdRng) -> Self {
+        Self {
+            candidate: start.clone(),
+            score,
+            rng,
+            steps: 0,
+            proposed: 0,
+            accepted: 0,
+        }
+    }
+
+    // One iteration at the given temperature. Continues with whether a new best was
+    // found, or breaks with the proposal (& its analysis) if the scorer stopped us.
+    #[allow(clippy::too_many_arguments)]
+    fn step<F: FnMut(&Term) -> Option<f64>, L: Language, O: Observer>(
+        &mut self,
+        lang: &L,
+        ty: &Type,
+        policy: &MutationPolicy,
+        counter: &mut Counter<'_, L>,
+        scorer: &mut F,
+        temperature: f64,
+        best: &mut Best<L>,
+        observer: &mut O,
+    ) -> TypeResult<ControlFlow<(Term, Analysis<L>), bool>> {
+        self.steps += 1;
+
+        let kind = policy.choose(&mut self.rng);
+        let mut event = Event {
+            iteration: self.steps,
+            kind,
+            temperature,
+            size: self.candidate.size(),
+            score: self.score,
+            proposal: None,
+        };
+
         // g_ratio = g(x|x') / g(x'|x)
         let Some((proposal, analysis, g_ratio)) =
-            mutate(lang, &candidate, ty, &mut cache)
+            mutate(lang, &self.candidate, ty, kind, policy, counter, &mut self.rng)?
         else {
-            continue;
+            observer.observe(&event);
+            return Ok(ControlFlow::Continue(false));
         };
 
         let Some(proposal_score) = scorer(&proposal) else {
-            return (i, proposal, analysis);
+            event.proposal = Some(Proposed {
+                term: &proposal,
+                size: proposal.size(),
+                score: None,
+                g_ratio,
+                accepted: true,
+            });
+            observer.observe(&event);
+            return Ok(ControlFlow::Break((proposal, analysis)));
         };
 
-        if proposal_score > best_score {
-            best_analysis = analysis;
-            best_score = proposal_score;
-            best_candidate = proposal.clone();
-        }
-
-        let score_ratio = proposal_score / score;
+        self.proposed += 1;
 
-        let acceptance_prob = score_ratio * g_ratio;
+        let improved = proposal_score > best.score;
+        if improved {
+            best.analysis = analysis;
+            best.score = proposal_score;
+            best.term = proposal.clone();
+        }
 
-        if with_probability(acceptance_prob) {
-            candidate = proposal;
-            score = proposal_score;
+        let score_ratio = proposal_score / self.score;
+
+        // Targets score^(1/T) rather than the score itself
+        let acceptance_prob = score_ratio.powf(1. / temperature) * g_ratio;
+        let accepted = with_probability(&mut self.rng, acceptance_prob);
+
+        event.proposal = Some(Proposed {
+            term: &proposal,
+            size: proposal.size(),
+            score: Some(proposal_score),
+            g_ratio,
+            accepted,
+        });
+        observer.observe(&event);
+
+        if accepted {
+            self.accepted += 1;
+            self.candidate = proposal;
+            self.score = proposal_score;
         }
-    }
 
-    (i, best_candidate, best_analysis)
+        Ok(ControlFlow::Continue(improved))
+    }
 }
 
-// Mutates a &Term. Also returns g(x|x') / g(x'|x) [where x' is the proposal]
-fn mutate<L: Language>(
+// Mutates a &Term. Also returns g(x|x') / g(x'|x) [where x' is the proposal], or
+// None if no proposal could be made. Fails if `term` is not of type `ty`.
+fn mutate<L: Language, R: Rng + ?Sized>(
     lang: &L,
     term: &Term,
     ty: &Type,
-    cache: &mut SizeCache<L>,
-) -> Option<(Term, Analysis<L>, f64)> {
+    kind: MutationTy,
+    policy: &MutationPolicy,
+    counter: &mut Counter<'_, L>,
+    rng: &mut R,
+) -> TypeResult<Option<(Term, Analysis<L>, f64)>> {
     let ctxt = lang.context();
+    let small_size = policy.small_size::<L>();
+    let large_size = policy.large_size::<L>();
 
     use MutationTy::*;
-    match MutationTy::choose_replacement_kind() {
+    let proposed = match kind {
         HVar => {
-            let term_meta = annotate_term(term, &ctxt, ty);
+            let term_meta = annotate_term(term, &ctxt, ty)?;
 
             let (var_node, annotation, _) =
-                random_subnode(term, &term_meta, 1, 1);
+                random_subnode(term, &term_meta, 1, 1, rng);
 
-            let (_, replacement) = cache.sample(
-                lang,
+            let replacement = counter.sample(
                 annotation.decls,
                 &annotation.ty,
                 annotation.size,
+                rng,
             );
 
             let (new_var, var_analysis) = replacement.unwrap();
 
-            let (candidate, analysis) = replace_subnode(
+            let Some((candidate, analysis)) = replace_subnode(
                 lang,
                 term,
                 &term_meta,
                 var_node,
                 new_var,
                 var_analysis,
-            )?;
+            ) else {
+                return Ok(None);
+            };
 
             Some((candidate, analysis, 1.))
         }
         Small => {
-            let term_meta = annotate_term(term, &ctxt, ty);
+            let term_meta = annotate_term(term, &ctxt, ty)?;
 
             let (replacement_node, annotation, _) =
-                random_subnode(term, &term_meta, 2, L::SMALL_SIZE);
+                random_subnode(term, &term_meta, 2, small_size, rng);
 
-            let (_, replacement) = cache.sample(
-                lang,
+            let replacement = counter.sample(
                 annotation.decls,
                 &annotation.ty,
                 annotation.size,
+                rng,
             );
 
             let (new_term, new_analysis) = replacement.unwrap();
 
-            let (proposal, analysis) = replace_subnode(
+            let Some((proposal, analysis)) = replace_subnode(
                 lang,
                 term,
                 &term_meta,
                 replacement_node,
                 new_term,
                 new_analysis,
-            )?;
+            ) else {
+                return Ok(None);
+            };
 
             if !proposal.in_beta_normal_form() {
-                return None;
+                return Ok(None);
             }
 
             Some((proposal, analysis, 1.))
@@ -165,70 +532,81 @@ fn mutate<L: Language>(
             use rand::distributions::Distribution;
             use statrs::distribution::Binomial;
 
-            let term_meta = annotate_term(term, &ctxt, ty);
+            let term_meta = annotate_term(term, &ctxt, ty)?;
 
             let (replacement_node, annotation, subnode_count) =
-                random_subnode(term, &term_meta, 2, L::LARGE_SIZE);
+                random_subnode(term, &term_meta, 2, large_size, rng);
 
             if subnode_count == 0 {
-                return None;
+                return Ok(None);
             }
 
-            let ratio = annotation.size as f64 / L::LARGE_SIZE as f64;
+            let ratio = annotation.size as f64 / large_size as f64;
 
-            let size_distr = Binomial::new(ratio, L::LARGE_SIZE as u64).ok()?;
+            let Ok(size_distr) = Binomial::new(ratio, large_size as u64) else {
+                return Ok(None);
+            };
             let replacement_size: u64 =
-                size_distr.sample(&mut rand::thread_rng());
+                size_distr.sample(rng);
             let replacement_size = replacement_size as usize;
 
-            let (new_count, replacement) = cache.sample(
-                lang,
+            let new_count = counter.count(
                 annotation.decls.clone(),
                 &annotation.ty,
                 replacement_size,
             );
 
-            let (replacement, replacement_analysis) = replacement?;
+            let Some((replacement, replacement_analysis)) = counter.sample(
+                annotation.decls.clone(),
+                &annotation.ty,
+                replacement_size,
+                rng,
+            ) else {
+                return Ok(None);
+            };
 
-            let (proposal, analysis) = replace_subnode(
+            let Some((proposal, analysis)) = replace_subnode(
                 lang,
                 term,
                 &term_meta,
                 replacement_node,
                 replacement,
                 replacement_analysis,
-            )?;
+            ) else {
+                return Ok(None);
+            };
 
             if !proposal.in_beta_normal_form() {
-                return None;
+                return Ok(None);
             }
 
-            let old_count = cache.query_count(
-                lang,
+            let old_count = counter.count(
                 annotation.decls,
                 &annotation.ty,
                 annotation.size,
             );
 
             // g1 = g(x' | x)
-            let g1 = g::<L>(
+            let g1 = g(
+                large_size,
                 subnode_count,
                 replacement_size,
                 annotation.size,
                 new_count,
             );
 
-            let prop_meta = annotate_term(&proposal, &ctxt, ty);
+            let prop_meta = annotate_term(&proposal, &ctxt, ty)?;
 
             let (_, _, subnode_count) =
-                random_subnode(&proposal, &prop_meta, 2, L::LARGE_SIZE);
+                random_subnode(&proposal, &prop_meta, 2, large_size, rng);
 
             if subnode_count == 0 {
-                return None;
+                return Ok(None);
             }
 
             //g2 = g(x | x')
-            let g2 = g::<L>(
+            let g2 = g(
+                large_size,
                 subnode_count,
                 annotation.size,
                 replacement_size,
@@ -237,23 +615,42 @@ fn mutate<L: Language>(
 
             Some((proposal, analysis, g2 / g1))
         }
-    }
+        Swap | Wrap | Unwrap | EtaExpand | EtaContract => {
+            let term_meta = annotate_term(term, &ctxt, ty)?;
+
+            match kind {
+                Swap => moves::swap(lang, &ctxt, term, &term_meta, ty, rng),
+                Wrap => moves::wrap(lang, &ctxt, term, &term_meta, ty, rng),
+                Unwrap => moves::unwrap(lang, &ctxt, term, &term_meta, ty, rng),
+                EtaExpand => moves::eta_expand(lang, &ctxt, term, &term_meta, ty, rng),
+                _ => moves::eta_contract(lang, &ctxt, term, ty, rng),
+            }
+        }
+    };
+
+    let Some((proposal, analysis, g_ratio)) = proposed else {
+        return Ok(None);
+    };
+
+    // Both g(x|x') & g(x'|x) include the probability of choosing their kind of mutation
+    Ok(Some((proposal, analysis, g_ratio * policy.inverse_ratio(kind))))
 }
 
 // g(x2 | x1)
-fn g<L: Language>(
+fn g(
+    large_size: usize,
     x1_subnode_count: usize,
     delta_size: usize,
     replaced_size: usize,
-    x2_num_replacement_terms: usize,
+    x2_num_replacement_terms: u128,
 ) -> f64 {
     use statrs::distribution::Binomial;
 
     let prob_subnode_selected = 1. / x1_subnode_count as f64;
 
-    let ratio = replaced_size as f64 / L::LARGE_SIZE as f64;
+    let ratio = replaced_size as f64 / large_size as f64;
 
-    let size_distr = Binomial::new(ratio, L::LARGE_SIZE as u64).unwrap();
+    let size_distr = Binomial::new(ratio, large_size as u64).unwrap();
 
     let prob_size_selected = size_distr.pmf(delta_size as u64);
 
@@ -357,11 +754,12 @@ pub fn replace_subnode<L: Language>(
 // Reservoir sampling, again.
 // We return the index of the subnode (using pre-order numbering) & its size
 // Returns (node_id, annotation, small_node_count)
-pub fn random_subnode(
+pub fn random_subnode<R: Rng + ?Sized>(
     term: &Term,
     meta: &Metadata,
     min_size: usize,
     max_size: usize,
+    rng: &mut R,
 ) -> (usize, Annotation, usize) {
     let mut selected_id: usize = 0;
     let mut stack = vec![(term.clone(), term as *const Term)];
@@ -376,7 +774,7 @@ pub fn random_subnode(
 
         if (min_size..=max_size).contains(&size) {
             small_counter += 1;
-            if with_probability(1. / small_counter as f64) {
+            if with_probability(rng, 1. / small_counter as f64) {
                 selected_id = counter;
                 annotation = meta.get(&ptr).unwrap();
             }
@@ -424,254 +822,257 @@ fn show_node_id(term: &Term, id: usize) {
     helper(term, id, &mut 0)
 }
 
-#[derive(Clone, Debug)]
-pub struct Annotation {
-    size: usize,
-    ty: Type,
-    decls: VarsVec, // Variables in scope
-}
-
-type Metadata = HashMap<*const Term, Annotation>;
-
-// Can fail if Term is not in beta-nf
-fn annotate_term(term: &Term, ctxt: &Context, ty: &Type) -> Metadata {
-    fn annotate(
-        term: &Term,
-        ctxt: &Context,
-        ty: Option<&Type>,
-        map: &mut Metadata,
-        decls: &VarsVec,
-    ) {
-        let ptr = term as *const Term;
-
-        if map.contains_key(&ptr) {
-            return;
-        }
-
-        use Term::*;
-        let annotation = match term {
-            Ref(r) => {
-                annotate(&r.borrow(), ctxt, ty, map, decls);
-
-                let ptr = r.as_ptr() as *const Term;
-
-                map.get(&ptr).unwrap().clone()
-            }
-            Val(_) => Annotation {
-                size: 1,
-                decls: decls.clone(),
-                ty: ty.unwrap().clone(),
-            },
-            Var(v) => {
-                if let Some((_, v_ty)) = decls.iter().find(|(s, _)| v == s) {
-                    Annotation {
-                        size: 1,
-                        ty: (**v_ty).clone(),
-                        decls: decls.clone(),
-                    }
-                } else if let Some(builtin) = ctxt.get(*v) {
-                    Annotation {
-                        size: 1,
-                        ty: (*builtin.ty).clone(),
-                        decls: decls.clone(),
-                    }
-                } else {
-                    panic!("Undeclared variable")
-                }
-            }
-            Lam(v, b) => {
-                let ty = ty.unwrap().clone();
-
-                let Type::Fun(arg, ret) = ty.clone() else {
-                    unimplemented!()
-                };
-
-                let decls = decls.clone();
-
-                let mut body_decls = decls.clone();
-                body_decls.push((*v, arg.clone()));
-
-                annotate(b, ctxt, Some(ret.as_ref()), map, &body_decls);
-
-                Annotation {
-                    size: term.size(),
-                    ty,
-                    decls,
-                }
-            }
-            App(l, r) => {
-                let f = l.as_ptr() as *const Term;
-
-                annotate(&l.borrow(), ctxt, None, map, decls);
-
-                let f_note = map.get(&f).unwrap().clone();
-
-                let Type::Fun(arg, ret) = f_note.ty else {
-                    unreachable!()
-                };
+type Metadata = Annotations;
 
-                annotate(&r.borrow(), ctxt, Some(&*arg), map, decls);
-
-                Annotation {
-                    size: term.size(),
-                    ty: (*ret).clone(),
-                    decls: f_note.decls,
-                }
-            }
-        };
+// Chains only contain well-typed terms, provided they start with one
+fn annotate_term(term: &Term, ctxt: &Context, ty: &Type) -> TypeResult<Metadata> {
+    annotate(ctxt, &vec![], term, ty)
+}
 
-        map.insert(ptr, annotation);
+impl From<TypeError> for MetropolisError {
+    fn from(err: TypeError) -> Self {
+        Self::Type(err)
     }
-
-    let mut map = Metadata::default();
-    annotate(term, ctxt, Some(ty), &mut map, &vec![]);
-    map
 }
 
-#[derive(Debug)]
-enum MutationTy {
-    HVar,
-    Small,
-    Large,
+impl From<PolicyError> for MetropolisError {
+    fn from(err: PolicyError) -> Self {
+        Self::Policy(err)
+    }
 }
 
-impl MutationTy {
-    pub fn choose_replacement_kind() -> Self {
-        let rand = random();
+impl From<ScheduleError> for MetropolisError {
+    fn from(err: ScheduleError) -> Self {
+        Self::Schedule(err)
+    }
+}
 
-        if rand < REPLACE_VAR {
-            Self::HVar
-        } else if rand < REPLACE_VAR + REPLACE_SMALL {
-            Self::Small
-        } else {
-            Self::Large
+impl Display for MetropolisError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use MetropolisError::*;
+        match self {
+            Type(err) => write!(f, "ill-typed start term: {}", err),
+            Policy(err) => write!(f, "invalid mutation policy: {}", err),
+            Schedule(err) => write!(f, "invalid temperature schedule: {}", err),
+            NoReplicas => write!(f, "replica exchange requires at least one replica"),
+            NoStart(reason) => write!(f, "no start term found: {}", reason),
         }
     }
 }
 
-type CtxtCache<L> = HashMap<(Type, usize), CacheEntry<L>>;
+impl std::error::Error for MetropolisError {}
 
-struct SizeCache<L: Language> {
-    map: HashMap<VarsVec, CtxtCache<L>>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::Polynomials;
 
-#[derive(Debug)]
-enum CacheEntry<L: Language> {
-    Explicit(Vec<(Term, Analysis<L>)>),
-    Count(usize),
-}
-
-impl<L: Language> SizeCache<L> {
-    const MAX_IN_MEM: usize = 32;
+    #[test]
+    fn ill_typed_start_is_an_error() {
+        let options = Options {
+            print_freq: None,
+            seed: Some(0),
+            ..Default::default()
+        };
 
-    pub fn sample(
-        &mut self,
-        lang: &L,
-        mut decls: VarsVec,
-        ty: &Type,
-        size: usize,
-    ) -> (usize, Option<(Term, Analysis<L>)>) {
-        use CacheEntry::*;
-
-        decls.sort();
-        let query = (ty.clone(), size);
-
-        let map = self.map.entry(decls.clone()).or_default();
-        if let Some(cache_entry) = map.get(&query) {
-            match cache_entry {
-                Count(0) => {
-                    return (0, None);
-                }
-                Explicit(explicit) => {
-                    let len = explicit.len();
-                    let id = (random() * len as f64) as usize;
+        let start = term!(x -> x);
+        let result = metropolis(
+            &Polynomials,
+            &start,
+            &ty!(N => N => N),
+            |_| Some(1.),
+            10,
+            options,
+        );
+
+        assert!(matches!(
+            result,
+            Err(MetropolisError::Type(TypeError::Mismatch { .. }))
+        ));
+    }
 
-                    return (explicit.len(), Some(explicit[id].clone()));
-                }
-                _ => (),
-            }
-        }
+    #[test]
+    fn same_seed_same_chain() {
+        let run = || {
+            let options = Options {
+                print_freq: None,
+                seed: Some(7),
+                ..Default::default()
+            };
 
-        let mut explicit = Vec::with_capacity(Self::MAX_IN_MEM);
+            // Every proposal, in order
+            let mut proposals = vec![];
+            let scorer = |term: &Term| {
+                proposals.push(term.to_string());
+                Some(-(term.to_string().len() as f64))
+            };
 
-        let mut search = search(lang, decls.clone(), ty, size);
+            let start = term!(x y -> plus x y);
+            let (i, best, _) = metropolis(
+                &Polynomials,
+                &start,
+                &ty!(N => N => N),
+                scorer,
+                300,
+                options,
+            )
+            .unwrap();
+
+            (i, best.to_string(), proposals)
+        };
 
-        while explicit.len() < Self::MAX_IN_MEM {
-            if let Some(next) = search.next() {
-                explicit.push(next);
-            } else if !explicit.is_empty() {
-                let len = explicit.len();
-                let id = (random() * len as f64) as usize;
+        let (first, second) = (run(), run());
+        assert!(first.2.len() > 1);
+        assert_eq!(first, second);
+    }
 
-                map.entry(query)
-                    .or_insert_with(|| Explicit(explicit.clone()));
+    fn replicas(temperatures: &[f64]) -> Vec<ReplicaStats> {
+        temperatures
+            .iter()
+            .map(|&temperature| ReplicaStats {
+                temperature,
+                ..Default::default()
+            })
+            .collect()
+    }
 
-                return (len, Some(explicit.swap_remove(id)));
-            } else {
-                map.entry(query).or_insert(Count(0));
-                return (0, None);
-            }
+    #[test]
+    fn exchanges_favour_the_better_candidate_when_cold() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut stats = replicas(&[1., 2.]);
+
+        // The hotter replica's candidate is better, so is always moved down
+        for _ in 0..100 {
+            let mut chains = [
+                Chain::new(&term!(a), 1., StdRng::seed_from_u64(0)),
+                Chain::new(&term!(b), 2., StdRng::seed_from_u64(0)),
+            ];
+            exchange(&mut chains, &mut stats, &mut rng);
+            assert_eq!(
+                (chains[0].candidate.to_string(), chains[0].score),
+                ("b".into(), 2.)
+            );
+            assert_eq!(
+                (chains[1].candidate.to_string(), chains[1].score),
+                ("a".into(), 1.)
+            );
         }
-
-        let (rest_count, selected) = reservoir_sample(search);
-
-        if selected.is_none() {
-            let len = explicit.len();
-            let id = (random() * len as f64) as usize;
-
-            map.entry(query)
-                .or_insert_with(|| Explicit(explicit.clone()));
-
-            return (len, Some(explicit.swap_remove(id)));
+        assert_eq!(
+            (stats[0].swaps_proposed, stats[0].swaps_accepted),
+            (100, 100)
+        );
+
+        // Otherwise with probability (1/2)^(1 - 1/2)
+        let mut stats = replicas(&[1., 2.]);
+        let trials = 10_000;
+        for _ in 0..trials {
+            let mut chains = [
+                Chain::new(&term!(a), 2., StdRng::seed_from_u64(0)),
+                Chain::new(&term!(b), 1., StdRng::seed_from_u64(0)),
+            ];
+            exchange(&mut chains, &mut stats, &mut rng);
         }
 
-        let total_count = Self::MAX_IN_MEM + rest_count;
-        let prob = rest_count as f64 / total_count as f64;
-
-        map.entry(query).or_insert(Count(total_count));
+        let rate = stats[0].swaps_accepted as f64 / trials as f64;
+        assert_eq!(stats[0].swaps_proposed, trials);
+        assert!((rate - 0.5f64.sqrt()).abs() < 0.02, "{}", rate);
+        // The hottest replica has no hotter neighbour
+        assert_eq!(stats[1].swaps_proposed, 0);
+    }
 
-        if with_probability(prob) {
-            let len = explicit.len();
-            let id = (random() * len as f64) as usize;
-            return (total_count, Some(explicit.swap_remove(id)));
+    #[test]
+    fn replica_stats() {
+        let options = Options {
+            print_freq: None,
+            seed: Some(0),
+            ..Default::default()
+        };
+        let tempering = Tempering::geometric(3, 4., 5);
+
+        let output = replica_exchange(
+            &Polynomials,
+            &term!(x y -> plus x y),
+            &ty!(N => N => N),
+            |term| Some(1. / term.size() as f64),
+            100,
+            &tempering,
+            options,
+        )
+        .unwrap();
+
+        assert_eq!(output.iterations, 100);
+        let temperatures: Vec<_> = output.replicas.iter().map(|r| r.temperature).collect();
+        assert_eq!(temperatures, [1., 2., 4.]);
+
+        for (k, replica) in output.replicas.iter().enumerate() {
+            assert!(replica.accepted <= replica.proposed);
+            assert!(replica.proposed <= 100);
+            assert!(replica.proposed > 0);
+            assert!(replica.swaps_accepted <= replica.swaps_proposed);
+
+            let swaps = if k + 1 < output.replicas.len() { 20 } else { 0 };
+            assert_eq!(replica.swaps_proposed, swaps);
         }
-
-        (total_count, selected)
     }
 
-    pub fn query_count(
-        &mut self,
-        lang: &L,
-        mut decls: VarsVec,
-        ty: &Type,
-        size: usize,
-    ) -> usize {
-        use CacheEntry::*;
-        let query = (ty.clone(), size);
-
-        decls.sort();
+    #[test]
+    fn invalid_options_are_errors() {
+        let run = |options: Options| {
+            let start = term!(x y -> plus x y);
+            let scorer = |_: &Term| Some(1.);
+            metropolis(&Polynomials, &start, &ty!(N => N => N), scorer, 10, options)
+        };
 
-        let map = self.map.entry(decls.clone()).or_default();
+        let mut options = Options {
+            print_freq: None,
+            seed: Some(0),
+            ..Default::default()
+        };
+        options.mutation.replace_var = -1.;
+        assert!(matches!(
+            run(options.clone()),
+            Err(MetropolisError::Policy(_))
+        ));
+
+        options.mutation = MutationPolicy::default();
+        options.schedule = Schedule::Constant(0.);
+        assert_eq!(
+            run(options).unwrap_err(),
+            MetropolisError::Schedule(ScheduleError::InvalidTemperature(0.))
+        );
+    }
 
-        if let Some(entry) = map.get(&query) {
-            return match entry {
-                Count(count) => *count,
-                Explicit(v) => v.len(),
+    #[test]
+    fn invalid_replicas_are_errors() {
+        let run = |temperatures: Vec<f64>| {
+            let options = Options {
+                print_freq: None,
+                seed: Some(0),
+                ..Default::default()
+            };
+            let tempering = Tempering {
+                temperatures,
+                swap_freq: 5,
             };
-        }
-
-        let count = search(lang, decls, ty, size).count();
-
-        map.insert(query, Count(count));
 
-        count
-    }
-}
+            replica_exchange(
+                &Polynomials,
+                &term!(x y -> plus x y),
+                &ty!(N => N => N),
+                |_| Some(1.),
+                10,
+                &tempering,
+                options,
+            )
+        };
 
-impl<L: Language> Default for SizeCache<L> {
-    fn default() -> Self {
-        Self {
-            map: Default::default(),
-        }
+        assert_eq!(run(vec![]).err(), Some(MetropolisError::NoReplicas));
+        assert_eq!(
+            run(vec![1., -2.]).err(),
+            Some(MetropolisError::Schedule(
+                ScheduleError::InvalidTemperature(-2.)
+            ))
+        );
+        assert!(run(vec![1., 2.]).is_ok());
     }
 }
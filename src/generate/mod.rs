@@ -1,8 +1,15 @@
 pub mod metro;
+mod moves;
+mod observe;
+mod policy;
+mod schedule;
 mod synth;
 mod utils;
 
 pub use metro::*;
+pub use observe::*;
+pub use policy::*;
+pub use schedule::*;
 pub use synth::*;
 
 use super::*;
@@ -0,0 +1,561 @@
+// Structural mutations, which rearrange the subterms of a term rather than
+// generating new ones. Each returns a proposal x' & its analysis, along with
+// g(x|x') / g(x'|x) for its kind of mutation & the inverse kind (the ratio of the
+// probabilities of choosing those kinds is applied by the caller). As with the
+// replacements in `metro.rs`, distinct choices leading to the same proposal
+// are not accounted for.
+
+use super::*;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::sync::Arc;
+
+// A subterm, numbered (in pre-order) as in `replace_subnode`
+struct Subterm {
+    id: usize,
+    term: Term,
+    ptr: *const Term,
+    is_head: bool, // Left child of an application
+}
+
+// Swaps two arguments (of equal type) of the same application
+pub(super) fn swap<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    ctxt: &Context,
+    term: &Term,
+    meta: &Annotations,
+    ty: &Type,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>, f64)> {
+    let swaps = swaps(term, meta);
+    let &(id, i, j) = swaps.choose(rng)?;
+
+    let (head, mut args) = spine(&subterms(term)[id - 1].term);
+    args.swap(i, j);
+
+    let spine = args
+        .into_iter()
+        .fold(head, |l, r| Term::App(l.into(), r.into()));
+
+    let proposal = graft(term, id, spine);
+    let (prop_meta, analysis) = finish(lang, ctxt, &proposal, ty)?;
+
+    let g_ratio = swaps.len() as f64 / self::swaps(&proposal, &prop_meta).len() as f64;
+
+    Some((proposal, analysis, g_ratio))
+}
+
+// Replaces some `t: T` with `w t`, for some `w: T -> T`
+pub(super) fn wrap<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    ctxt: &Context,
+    term: &Term,
+    meta: &Annotations,
+    ty: &Type,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>, f64)> {
+    let subterms = subterms(term);
+    let subterm = subterms.choose(rng)?;
+
+    let annotation = &meta[&subterm.ptr];
+    let wrappers = wrappers(ctxt, &annotation.decls, &annotation.ty);
+    let wrapper = *wrappers.choose(rng)?;
+
+    let wrapped = Term::App(Term::Var(wrapper).into(), subterm.term.clone().into());
+
+    let proposal = graft(term, subterm.id, wrapped);
+    let (prop_meta, analysis) = finish(lang, ctxt, &proposal, ty)?;
+
+    // g(x'|x) = 1/|subterms(x)| * 1/|wrappers|, g(x|x') = 1/|unwrappable(x')|
+    let g_ratio = (subterms.len() * wrappers.len()) as f64
+        / unwrappable(ctxt, &proposal, &prop_meta).len() as f64;
+
+    Some((proposal, analysis, g_ratio))
+}
+
+// Replaces some `w t` with `t` (where `w` could have been chosen by `wrap`)
+pub(super) fn unwrap<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    ctxt: &Context,
+    term: &Term,
+    meta: &Annotations,
+    ty: &Type,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>, f64)> {
+    let unwrappable = unwrappable(ctxt, term, meta);
+    let (id, inner, num_wrappers) = unwrappable.choose(rng)?.clone();
+
+    let proposal = graft(term, id, inner);
+    let (_, analysis) = finish(lang, ctxt, &proposal, ty)?;
+
+    // g(x'|x) = 1/|unwrappable(x)|, g(x|x') = 1/|subterms(x')| * 1/|wrappers|
+    let g_ratio =
+        unwrappable.len() as f64 / (subterms(&proposal).len() * num_wrappers) as f64;
+
+    Some((proposal, analysis, g_ratio))
+}
+
+// Replaces some `f` (of function type) with `\x -> f x`
+pub(super) fn eta_expand<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    ctxt: &Context,
+    term: &Term,
+    meta: &Annotations,
+    ty: &Type,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>, f64)> {
+    let expandable = expandable(term, meta);
+    let subterm = expandable.choose(rng)?;
+
+    let mut vgen = ctxt.vgen();
+    for ident in idents(term) {
+        vgen.retire(ident);
+    }
+    let var = vgen.newvar();
+
+    let body = Term::App(subterm.term.clone().into(), Term::Var(var).into());
+    let proposal = graft(term, subterm.id, Term::Lam(var, body.into()));
+    let (_, analysis) = finish(lang, ctxt, &proposal, ty)?;
+
+    let g_ratio = expandable.len() as f64 / contractible(&proposal).len() as f64;
+
+    Some((proposal, analysis, g_ratio))
+}
+
+// Replaces some `\x -> f x` (where `x` is not free in `f`) with `f`
+pub(super) fn eta_contract<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    ctxt: &Context,
+    term: &Term,
+    ty: &Type,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>, f64)> {
+    let contractible = contractible(term);
+    let (id, fun) = contractible.choose(rng)?.clone();
+
+    let proposal = graft(term, id, fun);
+    let (prop_meta, analysis) = finish(lang, ctxt, &proposal, ty)?;
+
+    // `eta_expand` picks its own variable, so it inverts this only up to
+    // α-equivalence (as do all moves through abstractions, chains being over terms
+    // up to renaming)
+    let g_ratio =
+        contractible.len() as f64 / expandable(&proposal, &prop_meta).len() as f64;
+
+    Some((proposal, analysis, g_ratio))
+}
+
+fn subterms(term: &Term) -> Vec<Subterm> {
+    let mut subterms = vec![];
+    let mut stack = vec![(term.clone(), term as *const Term, false)];
+
+    while let Some((next, ptr, is_head)) = stack.pop() {
+        use Term::*;
+        match &next {
+            Ref(r) => stack.push((r.borrow().clone(), r.as_ptr(), is_head)),
+            Lam(_, b) => stack.push(((**b).clone(), b.as_ref() as *const Term, false)),
+            App(l, r) => {
+                stack.push((r.borrow().clone(), r.as_ptr(), false));
+                stack.push((l.borrow().clone(), l.as_ptr(), true));
+            }
+            _ => (),
+        }
+
+        subterms.push(Subterm {
+            id: subterms.len() + 1,
+            term: next,
+            ptr,
+            is_head,
+        });
+    }
+
+    subterms
+}
+
+// Head & arguments of an application
+fn spine(term: &Term) -> (Term, Vec<Term>) {
+    let mut args = vec![];
+    let mut head = term.clone();
+
+    while let Term::App(l, r) = head {
+        args.push(r.borrow().clone());
+        head = l.borrow().clone();
+    }
+
+    args.reverse();
+    (head, args)
+}
+
+// (Application id, argument index, argument index) of each possible swap
+fn swaps(term: &Term, meta: &Annotations) -> Vec<(usize, usize, usize)> {
+    let mut swaps = vec![];
+
+    for subterm in subterms(term) {
+        if subterm.is_head || !matches!(subterm.term, Term::App(..)) {
+            continue;
+        }
+
+        let mut args = vec![];
+        let mut head = subterm.term.clone();
+        while let Term::App(l, r) = head {
+            args.push(meta[&(r.as_ptr() as *const Term)].ty.clone());
+            head = l.borrow().clone();
+        }
+        args.reverse();
+
+        for i in 0..args.len() {
+            for j in i + 1..args.len() {
+                if args[i] == args[j] {
+                    swaps.push((subterm.id, i, j));
+                }
+            }
+        }
+    }
+
+    swaps
+}
+
+// Variables in scope & builtins of type `ty -> ty`, sorted so that choices are reproducible
+fn wrappers(ctxt: &Context, decls: &VarsVec, ty: &Type) -> Vec<Identifier> {
+    let target = Type::Fun(Arc::new(ty.clone()), Arc::new(ty.clone()));
+
+    let mut seen = vec![];
+    let mut wrappers = vec![];
+
+    for (v, v_ty) in decls.iter().rev() {
+        if !seen.contains(v) {
+            seen.push(*v);
+            if **v_ty == target {
+                wrappers.push(*v);
+            }
+        }
+    }
+
+    for (v, builtin) in ctxt.iter() {
+        if seen.contains(v) {
+            continue;
+        }
+
+        let b_ty = TypeSub::instantiate(&builtin.params, &builtin.ty);
+        if TypeSub::default().unify(&b_ty, &target).is_some() {
+            wrappers.push(*v);
+        }
+    }
+
+    wrappers.sort();
+    wrappers
+}
+
+// (id, unwrapped term, number of wrappers) of each subterm `unwrap` may choose
+fn unwrappable(
+    ctxt: &Context,
+    term: &Term,
+    meta: &Annotations,
+) -> Vec<(usize, Term, usize)> {
+    let mut unwrappable = vec![];
+
+    for subterm in subterms(term) {
+        let Term::App(l, r) = &subterm.term else {
+            continue;
+        };
+
+        let w = match &*l.borrow() {
+            Term::Var(w) => *w,
+            _ => continue,
+        };
+
+        let annotation = &meta[&subterm.ptr];
+        if meta[&(r.as_ptr() as *const Term)].ty != annotation.ty {
+            continue;
+        }
+
+        let wrappers = wrappers(ctxt, &annotation.decls, &annotation.ty);
+        if wrappers.contains(&w) {
+            unwrappable.push((subterm.id, r.borrow().clone(), wrappers.len()));
+        }
+    }
+
+    unwrappable
+}
+
+// Subterms of function type which are neither abstractions nor heads of applications
+// (as their expansions would not be in beta-normal form)
+fn expandable(term: &Term, meta: &Annotations) -> Vec<Subterm> {
+    subterms(term)
+        .into_iter()
+        .filter(|s| !s.is_head && !matches!(s.term, Term::Lam(..)))
+        .filter(|s| matches!(meta[&s.ptr].ty, Type::Fun(..)))
+        .collect()
+}
+
+// (id, contracted term) of each subterm `eta_contract` may choose
+fn contractible(term: &Term) -> Vec<(usize, Term)> {
+    let mut contractible = vec![];
+
+    for subterm in subterms(term) {
+        let Term::Lam(v, b) = &subterm.term else {
+            continue;
+        };
+
+        let Term::App(f, x) = &**b else {
+            continue;
+        };
+
+        if matches!(&*x.borrow(), Term::Var(x) if x == v) && !occurs_free(&f.borrow(), *v) {
+            contractible.push((subterm.id, f.borrow().clone()));
+        }
+    }
+
+    contractible
+}
+
+fn occurs_free(term: &Term, var: Identifier) -> bool {
+    use Term::*;
+    match term {
+        Val(_) => false,
+        Var(v) => *v == var,
+        Lam(v, b) => *v != var && occurs_free(b, var),
+        App(l, r) => occurs_free(&l.borrow(), var) || occurs_free(&r.borrow(), var),
+        Ref(r) => occurs_free(&r.borrow(), var),
+    }
+}
+
+// Every identifier used in a term, free or bound
+fn idents(term: &Term) -> Vec<Identifier> {
+    subterms(term)
+        .into_iter()
+        .filter_map(|s| match s.term {
+            Term::Var(v) | Term::Lam(v, _) => Some(v),
+            _ => None,
+        })
+        .collect()
+}
+
+// Replaces the subterm numbered `id` with `src`
+fn graft(term: &Term, id: usize, src: Term) -> Term {
+    fn helper(counter: &mut usize, term: &Term, id: usize, src: &Term) -> Term {
+        *counter += 1;
+
+        if *counter == id {
+            return src.clone();
+        }
+
+        use Term::*;
+        match term {
+            Ref(r) => helper(counter, &r.borrow(), id, src),
+            Lam(v, b) => Lam(*v, helper(counter, b, id, src).into()),
+            App(l, r) => {
+                let l = helper(counter, &l.borrow(), id, src);
+                let r = helper(counter, &r.borrow(), id, src);
+                App(l.into(), r.into())
+            }
+            Val(_) | Var(_) => term.clone(),
+        }
+    }
+
+    helper(&mut 0, term, id, &src)
+}
+
+// Checks & analyzes a proposal, which must be well-typed, well-formed & in beta-normal form
+fn finish<L: Language>(
+    lang: &L,
+    ctxt: &Context,
+    proposal: &Term,
+    ty: &Type,
+) -> Option<(Annotations, Analysis<L>)> {
+    if !proposal.in_beta_normal_form() {
+        return None;
+    }
+
+    let meta = annotate(ctxt, &vec![], proposal, ty).ok()?;
+    let analysis = analyze(lang, proposal, &meta)?;
+
+    Some((meta, analysis))
+}
+
+// None if any subterm is malformed
+fn analyze<L: Language>(lang: &L, term: &Term, meta: &Annotations) -> Option<Analysis<L>> {
+    let ptr = term as *const Term;
+    let ty = &meta[&ptr].ty;
+
+    use Term::*;
+    let analysis = match term {
+        Ref(r) => return analyze(lang, &r.borrow(), meta),
+        Val(v) => lang.sval(v, ty),
+        Var(v) => lang.svar(*v, ty),
+        Lam(v, b) => lang.slam(*v, analyze(lang, b, meta)?, ty),
+        App(l, r) => {
+            let l = analyze(lang, &l.borrow(), meta)?;
+            let r = analyze(lang, &r.borrow(), meta)?;
+            lang.sapp(l, r, ty)
+        }
+    };
+
+    (!analysis.malformed()).then_some(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[derive(Clone, Debug)]
+    struct Moves;
+
+    impl Language for Moves {
+        type Semantics = OpaqueSemantics;
+
+        fn context(&self) -> Context {
+            let int = |t: &Term| t.get::<i32>();
+
+            let zero = builtin!(
+                N
+                | | => Term::val(0i32)
+            );
+
+            let succ = builtin!(
+                N => N
+                |x| => Term::val(int(&x)? + 1)
+            );
+
+            let plus = builtin!(
+                N => N => N
+                |x, y| => Term::val(int(&x)? + int(&y)?)
+            );
+
+            let twice = builtin!(
+                (N => N) => N => N
+                |f, x| => term!([f] ([f] [x]))
+            );
+
+            context! { zero, succ, plus, twice }
+        }
+    }
+
+    type Move = fn(&Term, &Annotations, &Type, &mut StdRng) -> Option<(Term, f64)>;
+
+    // Each proposal of `forward` which `backward` can undo is undone with the
+    // reciprocal ratio, as detailed balance needs
+    fn reciprocal(term: Term, ty: Type, forward: Move, backward: Move) {
+        let ctxt = Moves.context();
+        let meta = annotate(&ctxt, &vec![], &term, &ty).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut undone = 0;
+
+        for _ in 0..20 {
+            let Some((proposal, g_ratio)) = forward(&term, &meta, &ty, &mut rng) else {
+                continue;
+            };
+            let prop_meta = annotate(&ctxt, &vec![], &proposal, &ty).unwrap();
+
+            for _ in 0..20 {
+                let Some((back, back_ratio)) = backward(&proposal, &prop_meta, &ty, &mut rng)
+                else {
+                    continue;
+                };
+
+                if back.alpha_eq(&term) {
+                    let product = g_ratio * back_ratio;
+                    assert!(
+                        (product - 1.).abs() < 1e-9,
+                        "{} -> {}: {}",
+                        term,
+                        proposal,
+                        product
+                    );
+                    undone += 1;
+                }
+            }
+        }
+
+        assert!(undone > 0, "{} was never restored", term);
+    }
+
+    fn swap(term: &Term, meta: &Annotations, ty: &Type, rng: &mut StdRng) -> Option<(Term, f64)> {
+        let (term, _, g) = super::swap(&Moves, &Moves.context(), term, meta, ty, rng)?;
+        Some((term, g))
+    }
+
+    fn wrap(term: &Term, meta: &Annotations, ty: &Type, rng: &mut StdRng) -> Option<(Term, f64)> {
+        let (term, _, g) = super::wrap(&Moves, &Moves.context(), term, meta, ty, rng)?;
+        Some((term, g))
+    }
+
+    fn unwrap(term: &Term, meta: &Annotations, ty: &Type, rng: &mut StdRng) -> Option<(Term, f64)> {
+        let (term, _, g) = super::unwrap(&Moves, &Moves.context(), term, meta, ty, rng)?;
+        Some((term, g))
+    }
+
+    fn expand(term: &Term, meta: &Annotations, ty: &Type, rng: &mut StdRng) -> Option<(Term, f64)> {
+        let (term, _, g) = eta_expand(&Moves, &Moves.context(), term, meta, ty, rng)?;
+        Some((term, g))
+    }
+
+    fn contract(term: &Term, _: &Annotations, ty: &Type, rng: &mut StdRng) -> Option<(Term, f64)> {
+        let (term, _, g) = eta_contract(&Moves, &Moves.context(), term, ty, rng)?;
+        Some((term, g))
+    }
+
+    #[test]
+    fn swaps_are_reciprocal() {
+        reciprocal(
+            term!(x y -> plus x (plus y x)),
+            ty!(N => N => N),
+            swap,
+            swap,
+        );
+    }
+
+    #[test]
+    fn wraps_are_reciprocal() {
+        let ty = ty!((N => N) => N => N);
+        reciprocal(
+            term!(f x -> plus (f x) (succ zero)),
+            ty.clone(),
+            wrap,
+            unwrap,
+        );
+        reciprocal(term!(f x -> succ (f (succ x))), ty, unwrap, wrap);
+    }
+
+    #[test]
+    fn eta_moves_are_reciprocal() {
+        let ty = ty!((N => N) => N);
+        reciprocal(
+            term!(f -> twice (twice f) zero),
+            ty.clone(),
+            expand,
+            contract,
+        );
+        reciprocal(term!(f -> twice (x -> f x) zero), ty, contract, expand);
+    }
+
+    #[test]
+    fn contraction_needs_a_bound_argument() {
+        assert_eq!(contractible(&term!(x -> f x)).len(), 1);
+        assert!(contractible(&term!(x -> f y)).is_empty());
+        assert!(contractible(&term!(x -> x x)).is_empty());
+        // Only the inner abstraction, as `x` is free in `y -> x y`
+        assert_eq!(contractible(&term!(x -> (y -> x y) x)).len(), 1);
+    }
+
+    #[test]
+    fn wrappers_are_in_scope_or_builtins() {
+        let ctxt = Moves.context();
+        let decls = vec![
+            ("f".into(), Arc::new(ty!(N => N))),
+            ("g".into(), Arc::new(ty!(N => N => N))),
+        ];
+
+        let names: Vec<_> = wrappers(&ctxt, &decls, &ty!(N))
+            .iter()
+            .map(|w| w.as_str())
+            .collect();
+        assert_eq!(names, ["f", "succ"]);
+        assert_eq!(wrappers(&ctxt, &decls, &ty!(N => N)).len(), 1); // twice
+    }
+}
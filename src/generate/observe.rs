@@ -0,0 +1,166 @@
+// Observation of Metropolis chains, one event per iteration (see `metropolis_observed`).
+
+use super::*;
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug)]
+pub struct Event<'a> {
+    pub iteration: usize, // Of the chain, starting from 1
+    pub kind: MutationTy,
+    pub temperature: f64,
+    pub size: usize, // Of the current candidate (before this iteration)
+    pub score: f64,  // Of the current candidate (before this iteration)
+    pub proposal: Option<Proposed<'a>>, // None if the mutation failed to propose anything
+}
+
+#[derive(Clone, Debug)]
+pub struct Proposed<'a> {
+    pub term: &'a Term,
+    pub size: usize,
+    pub score: Option<f64>, // None if the scorer stopped the chain (so it was accepted)
+    pub g_ratio: f64,       // g(x|x') / g(x'|x)
+    pub accepted: bool,
+}
+
+pub trait Observer {
+    fn observe(&mut self, event: &Event);
+}
+
+impl Observer for () {
+    fn observe(&mut self, _: &Event) {}
+}
+
+impl<F: FnMut(&Event)> Observer for F {
+    fn observe(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+// Counts of each kind of mutation, by outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KindStats {
+    pub chosen: usize,
+    pub failed: usize, // Chosen, but produced no proposal
+    pub accepted: usize,
+}
+
+// Aggregates events by kind of mutation (in the order of `MutationTy::ALL`)
+#[derive(Clone, Debug, Default)]
+pub struct MutationStats {
+    kinds: [KindStats; MutationTy::ALL.len()],
+}
+
+fn index(kind: MutationTy) -> usize {
+    MutationTy::ALL.iter().position(|&k| k == kind).unwrap()
+}
+
+impl KindStats {
+    // Fraction of proposals accepted (None if nothing was proposed)
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        let proposed = self.chosen - self.failed;
+        (proposed > 0).then(|| self.accepted as f64 / proposed as f64)
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.chosen += other.chosen;
+        self.failed += other.failed;
+        self.accepted += other.accepted;
+    }
+}
+
+impl MutationStats {
+    pub fn get(&self, kind: MutationTy) -> KindStats {
+        self.kinds[index(kind)]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MutationTy, KindStats)> + '_ {
+        MutationTy::ALL.into_iter().map(|kind| (kind, self.get(kind)))
+    }
+
+    pub fn total(&self) -> KindStats {
+        let mut total = KindStats::default();
+        for stats in &self.kinds {
+            total.add(stats);
+        }
+        total
+    }
+}
+
+impl Observer for MutationStats {
+    fn observe(&mut self, event: &Event) {
+        let stats = &mut self.kinds[index(event.kind)];
+        stats.chosen += 1;
+
+        match &event.proposal {
+            None => stats.failed += 1,
+            Some(proposed) if proposed.accepted => stats.accepted += 1,
+            Some(_) => (),
+        }
+    }
+}
+
+impl Display for MutationStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (kind, stats) in self.iter().filter(|(_, s)| s.chosen > 0) {
+            let rate = stats.acceptance_rate().unwrap_or(0.);
+            writeln!(
+                f,
+                "{:<14} chosen {:>8}, failed {:>8}, accepted {:>8} ({:.1}%)",
+                kind,
+                stats.chosen,
+                stats.failed,
+                stats.accepted,
+                100. * rate
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_by_kind() {
+        let term = term!(x -> x);
+        let event = |kind, accepted: Option<bool>| Event {
+            iteration: 1,
+            kind,
+            temperature: 1.,
+            size: 1,
+            score: 1.,
+            proposal: accepted.map(|accepted| Proposed {
+                term: &term,
+                size: 1,
+                score: Some(1.),
+                g_ratio: 1.,
+                accepted,
+            }),
+        };
+
+        let mut stats = MutationStats::default();
+        stats.observe(&event(MutationTy::EtaContract, Some(true)));
+        stats.observe(&event(MutationTy::EtaContract, Some(false)));
+        stats.observe(&event(MutationTy::EtaContract, None));
+        stats.observe(&event(MutationTy::HVar, Some(true)));
+
+        let contract = stats.get(MutationTy::EtaContract);
+        assert_eq!(
+            (contract.chosen, contract.failed, contract.accepted),
+            (3, 1, 1)
+        );
+        assert_eq!(contract.acceptance_rate(), Some(0.5));
+        assert_eq!(stats.get(MutationTy::HVar).accepted, 1);
+        assert_eq!(stats.get(MutationTy::Small), KindStats::default());
+        assert_eq!(stats.total().chosen, 4);
+
+        let kinds: Vec<_> = stats
+            .iter()
+            .filter(|(_, s)| s.chosen > 0)
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(kinds, [MutationTy::HVar, MutationTy::EtaContract]);
+    }
+}
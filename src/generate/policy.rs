@@ -0,0 +1,345 @@
+// Proposal mix & subterm sizes used by `metropolis` to mutate its chain.
+
+use super::*;
+
+use rand::Rng;
+use std::fmt::{Display, Formatter};
+
+// Weights are relative, and need not sum to 1. Structural mutations (see
+// `moves.rs`) are disabled by default.
+#[derive(Clone, Copy, Debug)]
+pub struct MutationPolicy {
+    // Replacing a variable with another
+    pub replace_var: f64,
+    // Replacing a small (non-variable) subterm with another of equal size
+    pub replace_small: f64,
+    // Replacing a larger subterm with another, potentially of different size.
+    // This is much more computationally expensive and can erase a lot of progress, but also
+    // allows us to exit local minima (we must calculate g(x'|x) & g(x|x'), involving a census
+    // of terms we don't even use, so it should be much more unlikely than the others).
+    pub replace_large: f64,
+    pub swap: f64,         // Swapping two arguments of equal type in an application
+    pub wrap: f64,         // Applying a function of type `T -> T` to a subterm of type `T`
+    pub unwrap: f64,       // Inverse of `wrap`
+    pub eta_expand: f64,   // Replacing a subterm `f` with `\x -> f x`
+    pub eta_contract: f64, // Inverse of `eta_expand`
+    pub small_size: Option<usize>, // Max size of `small` subterms (`L::SMALL_SIZE` if None)
+    pub large_size: Option<usize>, // Max size of `large` subterms (`L::LARGE_SIZE` if None)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PolicyError {
+    InvalidWeight { kind: &'static str, weight: f64 }, // Negative, infinite or NaN
+    NoMutations,                                       // All weights are zero
+    // A mutation is enabled, but its inverse is not (so it could never be accepted)
+    Irreversible { kind: &'static str, inverse: &'static str },
+    SizeTooSmall { kind: &'static str, size: usize }, // Subterm sizes must be at least 2
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MutationTy {
+    HVar,
+    Small,
+    Large,
+    Swap,
+    Wrap,
+    Unwrap,
+    EtaExpand,
+    EtaContract,
+}
+
+impl Default for MutationPolicy {
+    fn default() -> Self {
+        Self {
+            replace_var: 0.5,
+            replace_small: 0.4,
+            replace_large: 0.1,
+            swap: 0.,
+            wrap: 0.,
+            unwrap: 0.,
+            eta_expand: 0.,
+            eta_contract: 0.,
+            small_size: None,
+            large_size: None,
+        }
+    }
+}
+
+impl MutationPolicy {
+    pub fn validate(&self) -> Result<(), PolicyError> {
+        for kind in MutationTy::ALL {
+            let weight = self.weight(kind);
+            if !weight.is_finite() || weight < 0. {
+                let kind = kind.name();
+                return Err(PolicyError::InvalidWeight { kind, weight });
+            }
+        }
+
+        if self.total_weight() == 0. {
+            return Err(PolicyError::NoMutations);
+        }
+
+        for kind in MutationTy::ALL {
+            let inverse = kind.inverse();
+            if self.weight(kind) > 0. && self.weight(inverse) == 0. {
+                return Err(PolicyError::Irreversible {
+                    kind: kind.name(),
+                    inverse: inverse.name(),
+                });
+            }
+        }
+
+        let sizes = [("small_size", self.small_size), ("large_size", self.large_size)];
+
+        for (kind, size) in sizes {
+            if let Some(size @ 0..=1) = size {
+                return Err(PolicyError::SizeTooSmall { kind, size });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn small_size<L: Language>(&self) -> usize {
+        self.small_size.unwrap_or(L::SMALL_SIZE)
+    }
+
+    pub fn large_size<L: Language>(&self) -> usize {
+        self.large_size.unwrap_or(L::LARGE_SIZE)
+    }
+
+    fn weight(&self, kind: MutationTy) -> f64 {
+        use MutationTy::*;
+        match kind {
+            HVar => self.replace_var,
+            Small => self.replace_small,
+            Large => self.replace_large,
+            Swap => self.swap,
+            Wrap => self.wrap,
+            Unwrap => self.unwrap,
+            EtaExpand => self.eta_expand,
+            EtaContract => self.eta_contract,
+        }
+    }
+
+    fn total_weight(&self) -> f64 {
+        MutationTy::ALL.iter().map(|k| self.weight(*k)).sum()
+    }
+
+    // P(`kind.inverse()` is chosen) / P(`kind` is chosen)
+    pub(super) fn inverse_ratio(&self, kind: MutationTy) -> f64 {
+        self.weight(kind.inverse()) / self.weight(kind)
+    }
+
+    pub(super) fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> MutationTy {
+        let mut rand = rng.gen::<f64>() * self.total_weight();
+
+        for kind in MutationTy::ALL {
+            let weight = self.weight(kind);
+            if rand < weight {
+                return kind;
+            }
+            rand -= weight;
+        }
+
+        // Only reachable through rounding, so fall back to the last enabled kind
+        MutationTy::ALL
+            .into_iter()
+            .rev()
+            .find(|k| self.weight(*k) > 0.)
+            .unwrap()
+    }
+}
+
+impl MutationTy {
+    pub const ALL: [Self; 8] = [
+        Self::HVar,
+        Self::Small,
+        Self::Large,
+        Self::Swap,
+        Self::Wrap,
+        Self::Unwrap,
+        Self::EtaExpand,
+        Self::EtaContract,
+    ];
+
+    // The kind of mutation which can undo this one
+    fn inverse(self) -> Self {
+        use MutationTy::*;
+        match self {
+            Wrap => Unwrap,
+            Unwrap => Wrap,
+            EtaExpand => EtaContract,
+            EtaContract => EtaExpand,
+            kind => kind,
+        }
+    }
+
+    // As the corresponding field of `MutationPolicy`
+    pub fn name(self) -> &'static str {
+        use MutationTy::*;
+        match self {
+            HVar => "replace_var",
+            Small => "replace_small",
+            Large => "replace_large",
+            Swap => "swap",
+            Wrap => "wrap",
+            Unwrap => "unwrap",
+            EtaExpand => "eta_expand",
+            EtaContract => "eta_contract",
+        }
+    }
+}
+
+impl Display for MutationTy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+impl Display for PolicyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use PolicyError::*;
+        match self {
+            InvalidWeight { kind, weight } => {
+                write!(f, "`{}` must be a non-negative weight, but is {}", kind, weight)
+            }
+            NoMutations => write!(f, "at least one mutation must have a positive weight"),
+            Irreversible { kind, inverse } => {
+                write!(f, "`{}` is enabled, so `{}` must be too", kind, inverse)
+            }
+            SizeTooSmall { kind, size } => {
+                write!(f, "`{}` must be at least 2, but is {}", kind, size)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn default_is_valid() {
+        assert_eq!(MutationPolicy::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validation() {
+        use PolicyError::*;
+
+        let policy = |f: fn(&mut MutationPolicy)| {
+            let mut policy = MutationPolicy::default();
+            f(&mut policy);
+            policy.validate()
+        };
+
+        assert_eq!(
+            policy(|p| p.replace_small = -0.5),
+            Err(InvalidWeight {
+                kind: "replace_small",
+                weight: -0.5
+            })
+        );
+        assert_eq!(
+            policy(|p| p.swap = f64::INFINITY),
+            Err(InvalidWeight {
+                kind: "swap",
+                weight: f64::INFINITY
+            })
+        );
+        assert!(matches!(
+            policy(|p| p.replace_var = f64::NAN),
+            Err(InvalidWeight {
+                kind: "replace_var",
+                ..
+            })
+        ));
+
+        assert_eq!(
+            policy(|p| {
+                p.replace_var = 0.;
+                p.replace_small = 0.;
+                p.replace_large = 0.;
+            }),
+            Err(NoMutations)
+        );
+
+        assert_eq!(
+            policy(|p| p.wrap = 0.1),
+            Err(Irreversible {
+                kind: "wrap",
+                inverse: "unwrap"
+            })
+        );
+        assert_eq!(
+            policy(|p| p.eta_contract = 0.1),
+            Err(Irreversible {
+                kind: "eta_contract",
+                inverse: "eta_expand"
+            })
+        );
+        assert_eq!(
+            policy(|p| {
+                p.eta_expand = 0.1;
+                p.eta_contract = 0.2;
+            }),
+            Ok(())
+        );
+
+        assert_eq!(
+            policy(|p| p.small_size = Some(1)),
+            Err(SizeTooSmall {
+                kind: "small_size",
+                size: 1
+            })
+        );
+        assert_eq!(
+            policy(|p| p.large_size = Some(0)),
+            Err(SizeTooSmall {
+                kind: "large_size",
+                size: 0
+            })
+        );
+        assert_eq!(policy(|p| p.small_size = Some(2)), Ok(()));
+    }
+
+    #[test]
+    fn choices_follow_weights() {
+        let policy = MutationPolicy {
+            replace_var: 0.4,
+            replace_small: 0.2,
+            replace_large: 0.,
+            swap: 0.1,
+            wrap: 0.2,
+            unwrap: 0.1,
+            ..Default::default()
+        };
+        assert_eq!(policy.validate(), Ok(()));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut chosen = [0; MutationTy::ALL.len()];
+        let draws = 100_000;
+        for _ in 0..draws {
+            let kind = policy.choose(&mut rng);
+            chosen[MutationTy::ALL.iter().position(|&k| k == kind).unwrap()] += 1;
+        }
+
+        for (kind, times) in MutationTy::ALL.into_iter().zip(chosen) {
+            let expected = policy.weight(kind) / policy.total_weight();
+            let freq = times as f64 / draws as f64;
+            assert!((freq - expected).abs() < 0.01, "{}: {}", kind, freq);
+        }
+
+        use MutationTy::*;
+        assert_eq!(policy.inverse_ratio(Wrap), 0.5);
+        assert_eq!(policy.inverse_ratio(Unwrap), 2.);
+        assert_eq!(policy.inverse_ratio(Swap), 1.);
+        assert_eq!(policy.inverse_ratio(HVar), 1.);
+    }
+}
@@ -0,0 +1,185 @@
+// Temperature schedules for `metropolis`. At temperature T, a chain targets
+// score^(1/T), so hot chains wander freely while cold ones mostly climb.
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Schedule {
+    Constant(f64),
+    // T = max(start * rate^i, min)
+    Geometric { start: f64, rate: f64, min: f64 },
+    // As `Geometric`, but starts over after `patience` iterations without a new best score
+    Reheating {
+        start: f64,
+        rate: f64,
+        min: f64,
+        patience: usize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScheduleError {
+    InvalidTemperature(f64), // Temperatures must be positive & finite
+    InvalidRate(f64),        // Cooling rates must be in (0, 1]
+    MinAboveStart(f64, f64), // Cooling can't raise the temperature
+    NoPatience,
+}
+
+// Tracks the temperature of a chain as it runs
+#[derive(Clone, Debug)]
+pub(super) struct Thermostat {
+    schedule: Schedule,
+    temperature: f64,
+    since_reheat: usize,
+    since_best: usize,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::Constant(1.)
+    }
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), ScheduleError> {
+        use Schedule::*;
+        let (temperatures, rate) = match *self {
+            Constant(t) => (vec![t], 1.),
+            Geometric { start, rate, min } => (vec![start, min], rate),
+            Reheating {
+                start,
+                rate,
+                min,
+                patience,
+            } => {
+                if patience == 0 {
+                    return Err(ScheduleError::NoPatience);
+                }
+                (vec![start, min], rate)
+            }
+        };
+
+        for &t in &temperatures {
+            if !t.is_finite() || t <= 0. {
+                return Err(ScheduleError::InvalidTemperature(t));
+            }
+        }
+
+        if !(rate > 0. && rate <= 1.) {
+            return Err(ScheduleError::InvalidRate(rate));
+        }
+
+        if let [start, min] = temperatures[..] {
+            if min > start {
+                return Err(ScheduleError::MinAboveStart(min, start));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Temperature after `i` iterations of cooling
+    pub fn temperature(&self, i: usize) -> f64 {
+        use Schedule::*;
+        match *self {
+            Constant(t) => t,
+            Geometric { start, rate, min } | Reheating { start, rate, min, .. } => {
+                (start * rate.powf(i as f64)).max(min)
+            }
+        }
+    }
+}
+
+impl Thermostat {
+    pub fn new(schedule: Schedule) -> Self {
+        Self {
+            schedule,
+            temperature: schedule.temperature(0),
+            since_reheat: 0,
+            since_best: 0,
+        }
+    }
+
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    // Called once per iteration, noting whether a new best score was found
+    pub fn step(&mut self, improved: bool) {
+        self.since_reheat += 1;
+        self.since_best = if improved { 0 } else { self.since_best + 1 };
+
+        if let Schedule::Reheating { patience, .. } = self.schedule {
+            if self.since_best >= patience {
+                self.since_reheat = 0;
+                self.since_best = 0;
+            }
+        }
+
+        self.temperature = self.schedule.temperature(self.since_reheat);
+    }
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use ScheduleError::*;
+        match self {
+            InvalidTemperature(t) => {
+                write!(f, "temperatures must be positive & finite, but found {}", t)
+            }
+            InvalidRate(r) => write!(f, "cooling rates must be in (0, 1], but found {}", r),
+            MinAboveStart(min, start) => {
+                write!(f, "minimum temperature {} is above the start {}", min, start)
+            }
+            NoPatience => write!(f, "reheating requires a positive patience"),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        use Schedule::*;
+        use ScheduleError::*;
+
+        let geometric = |start, rate, min| Geometric { start, rate, min };
+        let reheating = |start, min, patience| Reheating {
+            start,
+            rate: 0.9,
+            min,
+            patience,
+        };
+
+        assert_eq!(Constant(1.).validate(), Ok(()));
+        assert_eq!(Constant(0.).validate(), Err(InvalidTemperature(0.)));
+        assert_eq!(geometric(4., 0.9, 0.5).validate(), Ok(()));
+        assert_eq!(geometric(4., 1.5, 0.5).validate(), Err(InvalidRate(1.5)));
+        assert_eq!(geometric(1., 0.9, 2.).validate(), Err(MinAboveStart(2., 1.)));
+        assert_eq!(reheating(4., 0.5, 10).validate(), Ok(()));
+        assert_eq!(reheating(1., 2., 10).validate(), Err(MinAboveStart(2., 1.)));
+        assert_eq!(reheating(4., 0.5, 0).validate(), Err(NoPatience));
+    }
+
+    #[test]
+    fn reheats_without_improvement() {
+        let mut thermostat = Thermostat::new(Schedule::Reheating {
+            start: 4.,
+            rate: 0.5,
+            min: 0.5,
+            patience: 3,
+        });
+
+        thermostat.step(true);
+        thermostat.step(false);
+        assert_eq!(thermostat.temperature(), 1.);
+        thermostat.step(false);
+        assert_eq!(thermostat.temperature(), 0.5);
+        thermostat.step(false);
+        assert_eq!(thermostat.temperature(), 4.);
+    }
+}
@@ -7,51 +7,63 @@ pub fn iterative<L, I, O>(
     ty: Type,
     settings: SynthesisParameters,
     options: Options,
-) -> MetropolisOutput<L>
+) -> MetropolisResult<MetropolisOutput<L>>
 where
     L: Language,
     I: TermValue + Clone,
     O: TermValue + Clone,
 {
-    let start = start.unwrap_or_else(|| {
-        (1..)
-            .flat_map(|size| search(&lang, vec![], &ty, size))
-            .next()
-            .unwrap()
-            .0
-    });
+    iterative_observed(lang, seed, examples, start, ty, settings, options, &mut ())
+}
+
+// As `iterative`, reporting each iteration of every chain to `observer`
+#[allow(clippy::too_many_arguments)]
+pub fn iterative_observed<L, I, O, Obs>(
+    lang: L,
+    seed: O,
+    examples: impl Iterator<Item = (I, O)>,
+    start: Option<Term>,
+    ty: Type,
+    settings: SynthesisParameters,
+    options: Options,
+    observer: &mut Obs,
+) -> MetropolisResult<MetropolisOutput<L>>
+where
+    L: Language,
+    I: TermValue + Clone,
+    O: TermValue + Clone,
+    Obs: Observer,
+{
+    let start = match start {
+        Some(start) => start,
+        None => smallest_term(&lang, &ty, 1, &options.budget)?,
+    };
 
-    let seed_term = Term::val(seed);
+    let seed: Value = std::sync::Arc::new(seed);
 
     let examples = examples
-        .map(|(i, o)| (Term::val(i), std::rc::Rc::new(o)))
+        .map(|(i, o)| (std::sync::Arc::new(i) as Value, std::sync::Arc::new(o)))
         .collect::<Vec<_>>();
 
     let num_examples = examples.len();
 
-    let lang_ctxt = lang.context();
+    let lang_ctxt = scoring_context(&lang, &settings);
+    let budget = options.budget.clone();
 
     let int_scorer = |t: &Term| {
         let mut num_correct = 0;
 
-        let mut yielded = seed_term.clone();
+        let program = lang_ctxt.compile(t);
+        let mut yielded = seed.clone();
 
         for (i, o) in examples.iter() {
-            let program = term! {
-                [t] [yielded] [i]
-            };
-
-            let evaled = lang_ctxt.evaluate(&program);
+            let output = eval_output(&program, &[yielded, i.clone()], &settings, &budget);
 
-            let Some(output) = evaled.leaf_val() else {
-                unimplemented!("Term `{}` did not evaluate to value.", evaled);
-            };
-
-            if o.is_eq(&output) {
+            if output.is_ok_and(|output| o.is_eq(&output)) {
                 num_correct += 1;
             }
 
-            yielded = Term::Val(o.clone());
+            yielded = o.clone();
         }
 
         num_correct
@@ -69,19 +81,30 @@ where
     };
 
     let start_time = std::time::Instant::now();
-    let (iterations, term, analysis) =
-        metropolis(&lang, &start, &ty, scorer, settings.iterations, options);
+    let mut mutations = MutationStats::default();
+    let mut observe = |event: &Event| {
+        mutations.observe(event);
+        observer.observe(event);
+    };
+    let (chains, iterations, term, analysis) =
+        run_chains(&lang, &start, &ty, scorer, &settings, &options, &mut observe)?;
     let end_time = std::time::Instant::now();
 
     let num_correct = int_scorer(&term);
     let score = scorer(&term);
 
-    MetropolisOutput {
+    Ok(MetropolisOutput {
         term,
         iterations,
+        chains,
         time: end_time.duration_since(start_time).as_secs_f64(),
         num_correct,
         score,
         analysis,
-    }
+        memo: lang_ctxt.memo_stats(),
+        stopped: budget.stop_reason(),
+        mutations,
+        settings,
+        seed: options.seed,
+    })
 }
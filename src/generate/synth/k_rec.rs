@@ -9,37 +9,52 @@ pub fn k_rec<L, O>(
     ty: Type,
     settings: SynthesisParameters,
     options: Options,
-) -> MetropolisOutput<L>
+) -> MetropolisResult<MetropolisOutput<L>>
 where
     L: Language,
     O: TermValue + Clone,
 {
-    let mut seeds = examples.map(Term::val::<O>).collect::<Vec<_>>();
+    k_rec_observed(lang, k, examples, start, ty, settings, options, &mut ())
+}
+
+// As `k_rec`, reporting each iteration of every chain to `observer`
+#[allow(clippy::too_many_arguments)]
+pub fn k_rec_observed<L, O, Obs>(
+    lang: L,
+    k: usize,
+    examples: impl Iterator<Item = O>,
+    start: Term,
+    ty: Type,
+    settings: SynthesisParameters,
+    options: Options,
+    observer: &mut Obs,
+) -> MetropolisResult<MetropolisOutput<L>>
+where
+    L: Language,
+    O: TermValue + Clone,
+    Obs: Observer,
+{
+    let mut seeds = examples
+        .map(|o| std::sync::Arc::new(o) as Value)
+        .collect::<Vec<_>>();
 
     let examples = seeds.split_off(k);
 
     let num_examples = examples.len();
 
-    let lang_ctxt = lang.context();
+    let lang_ctxt = scoring_context(&lang, &settings);
+    let budget = options.budget.clone();
 
     let int_scorer = |t: &Term| {
         let mut num_correct = 0;
 
+        let program = lang_ctxt.compile(t);
         let mut prevs = VecDeque::from(seeds.clone());
 
         for o in examples.iter() {
-            let mut program = t.clone();
-            for prev in prevs.iter() {
-                program = Term::App(program.into(), prev.clone().into());
-            }
-
-            let evaled = lang_ctxt.evaluate(&program);
+            let output = eval_output(&program, prevs.make_contiguous(), &settings, &budget);
 
-            let Some(output) = evaled.leaf_val() else {
-                unimplemented!("term `{}` did not evaluate to value.", evaled);
-            };
-
-            if o.get::<O>().is_eq(&output) {
+            if output.is_ok_and(|output| o.is_eq(&output)) {
                 num_correct += 1;
             }
 
@@ -62,19 +77,30 @@ where
     };
 
     let start_time = std::time::Instant::now();
-    let (iterations, term, analysis) =
-        metropolis(&lang, &start, &ty, scorer, settings.iterations, options);
+    let mut mutations = MutationStats::default();
+    let mut observe = |event: &Event| {
+        mutations.observe(event);
+        observer.observe(event);
+    };
+    let (chains, iterations, term, analysis) =
+        run_chains(&lang, &start, &ty, scorer, &settings, &options, &mut observe)?;
     let end_time = std::time::Instant::now();
 
     let num_correct = int_scorer(&term);
     let score = scorer(&term);
 
-    MetropolisOutput {
+    Ok(MetropolisOutput {
         term,
         iterations,
+        chains,
         time: end_time.duration_since(start_time).as_secs_f64(),
         num_correct,
         score,
         analysis,
-    }
+        memo: lang_ctxt.memo_stats(),
+        stopped: budget.stop_reason(),
+        mutations,
+        settings,
+        seed: options.seed,
+    })
 }
@@ -10,21 +10,36 @@ pub use simple_map::*;
 
 use super::*;
 
+use serde::{Deserialize, Serialize};
 use statrs::distribution::{Continuous, Normal};
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct SynthesisParameters {
     pub bias: SizeBias,
     pub score_factor: f64,
     pub iterations: usize,
+    pub fuel: usize,      // Max reduction steps per example
+    pub max_depth: usize, // Max evaluator recursion depth per example
+    pub memoize: bool,    // Memoize builtin calls across examples & candidates
+    // Independent chains (each of up to `iterations`), run one after another (as they
+    // share the scorer) until one finds a solution. Chains may also be stopped early
+    // by `Options::patience`.
+    pub chains: usize,
 }
 
 pub struct MetropolisOutput<L: Language> {
     pub term: Term,
     pub iterations: usize,
+    pub chains: usize, // Chains run (iterations are summed over them)
     pub time: f64,
     pub num_correct: usize,
     pub score: Option<f64>,
     pub analysis: Analysis<L>,
+    pub memo: Option<MemoStats>,
+    pub stopped: Option<StopReason>, // Set if the run was cut short by its budget
+    pub mutations: MutationStats,    // Over all chains
+    pub settings: SynthesisParameters,
+    pub seed: Option<u64>, // As in `Options`
 }
 
 impl Default for SynthesisParameters {
@@ -33,12 +48,131 @@ impl Default for SynthesisParameters {
             bias: SizeBias::Unbiased,
             score_factor: 0.5,
             iterations: 50_000,
+            fuel: 1_000_000,
+            max_depth: 4_096,
+            memoize: false,
+            chains: 1,
         }
     }
 }
 
+fn scoring_context<L: Language>(lang: &L, settings: &SynthesisParameters) -> Context {
+    let ctxt = lang.context();
+    if settings.memoize {
+        ctxt.with_memo()
+    } else {
+        ctxt
+    }
+}
+
+// Runs up to `settings.chains` chains in turn, with distinct seeds (if seeded), returning
+// the number of chains run, their total iterations & the best term found by any of them.
+// No further chains are started once `options.budget` is exhausted.
+fn run_chains<L: Language, F: FnMut(&Term) -> Option<f64>, Obs: Observer>(
+    lang: &L,
+    start: &Term,
+    ty: &Type,
+    mut scorer: F,
+    settings: &SynthesisParameters,
+    options: &Options,
+    observer: &mut Obs,
+) -> MetropolisResult<(usize, usize, Term, Analysis<L>)> {
+    let mut total_iterations = 0;
+    let mut best: Option<(f64, Term, Analysis<L>)> = None;
+
+    for chain in 0..settings.chains.max(1) {
+        let chain_options = Options {
+            seed: options.seed.map(|seed| chain_seed(seed, chain as u64)),
+            ..options.clone()
+        };
+
+        let (iterations, term, score, analysis) = metropolis_observed(
+            lang,
+            start,
+            ty,
+            &mut scorer,
+            settings.iterations,
+            chain_options,
+            observer,
+        )?;
+        total_iterations += iterations;
+
+        // A solution stops every chain
+        let Some(score) = score else {
+            return Ok((chain + 1, total_iterations, term, analysis));
+        };
+
+        if best.as_ref().is_none_or(|(best_score, _, _)| score > *best_score) {
+            best = Some((score, term, analysis));
+        }
+
+        if options.budget.check().is_some() {
+            let (_, term, analysis) = best.unwrap();
+            return Ok((chain + 1, total_iterations, term, analysis));
+        }
+    }
+
+    let (_, term, analysis) = best.unwrap();
+    Ok((settings.chains.max(1), total_iterations, term, analysis))
+}
+
+// Seed of the `chain`th chain of a run seeded with `seed`. Mixed (by SplitMix64's
+// finalizer), so that the chains of nearby seeds don't overlap
+fn chain_seed(seed: u64, chain: u64) -> u64 {
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // The first chain keeps the seed, so that one chain is as `metropolis`
+    if chain == 0 {
+        seed
+    } else {
+        mix(mix(seed) ^ chain)
+    }
+}
+
+// Default start: the first term of type `ty` (& size at least `min_size`) found
+// by enumeration. Never returns if `ty` is uninhabited, unless `budget` is limited.
+fn smallest_term<L: Language>(
+    lang: &L,
+    ty: &Type,
+    min_size: usize,
+    budget: &Budget,
+) -> MetropolisResult<Term> {
+    let mut size = min_size;
+    loop {
+        let mut terms = search(lang, vec![], ty, size).with_budget(budget.clone());
+        if let Some((term, _)) = terms.next() {
+            return Ok(term);
+        }
+
+        if let Some(reason) = budget.check() {
+            return Err(MetropolisError::NoStart(reason));
+        }
+
+        size += 1;
+    }
+}
+
+// Runs a (compiled) candidate on one example. Candidates which fail to evaluate
+// to a value (e.g. by exceeding the evaluation limits) are scored as wrong on it.
+// Steps are charged to `budget` (whose limit is only checked between iterations).
+fn eval_output(
+    program: &Compiled,
+    args: &[Value],
+    settings: &SynthesisParameters,
+    budget: &Budget,
+) -> std::result::Result<Value, EvalError> {
+    let (output, steps) = program.run_counting_steps(args, settings.fuel, settings.max_depth);
+    budget.charge_steps(steps);
+
+    output
+}
+
 // Used to bias programs towards reasonable sizes / prevent runaway term sizes
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum SizeBias {
     Unbiased,
     LinearBeyond { cutoff: usize, c: f64 },
@@ -62,11 +196,7 @@ impl SizeBias {
                 score * normal.pdf(size as f64)
             }
             DistAbs { mean, c } => {
-                let dist = if mean >= size {
-                    mean - size
-                } else {
-                    size - mean
-                };
+                let dist = mean.abs_diff(size);
 
                 let punishment = -c * dist as f64;
 
@@ -81,10 +211,15 @@ impl<L: Language> MetropolisOutput<L> {
         let MetropolisOutput {
             term,
             iterations,
+            chains,
             time,
             num_correct,
             score,
             analysis,
+            memo,
+            stopped,
+            mutations,
+            ..
         } = self;
 
         println!("Best Found: {}", &term);
@@ -92,8 +227,106 @@ impl<L: Language> MetropolisOutput<L> {
 
         println!("Score: {:?} (or {:?} correct)", score, num_correct,);
 
-        println!("Iterations: {}", iterations);
+        println!("Iterations: {} (over {} chains)", iterations, chains);
         println!("Time (s): {}", time);
         println!("Time (s/iter): {}", time / *iterations as f64);
+
+        let total = mutations.total();
+        println!(
+            "Acceptance rate: {:.1}%",
+            100. * total.acceptance_rate().unwrap_or(0.)
+        );
+        print!("{}", mutations);
+
+        if let Some(reason) = stopped {
+            println!("Stopped early: {}", reason);
+        }
+
+        if let Some(MemoStats {
+            hits,
+            misses,
+            entries,
+        }) = memo
+        {
+            println!("Memo: {} hits, {} misses, {} entries", hits, misses, entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::Polynomials;
+
+    #[test]
+    fn drivers_report_to_observers() {
+        let mut events = 0;
+        let mut kinds = MutationStats::default();
+        let mut observer = |event: &Event| {
+            events += 1;
+            kinds.observe(event);
+        };
+
+        let output = simple_map_observed(
+            Polynomials,
+            (0..5).map(|n| (n, n * n * n + 7)),
+            None,
+            ty!(N => N),
+            SynthesisParameters {
+                iterations: 50,
+                chains: 2,
+                ..Default::default()
+            },
+            Options {
+                print_freq: None,
+                seed: Some(0),
+                ..Default::default()
+            },
+            &mut observer,
+        )
+        .unwrap();
+
+        assert_eq!(events, output.iterations);
+        for kind in MutationTy::ALL {
+            assert_eq!(kinds.get(kind), output.mutations.get(kind), "{}", kind);
+        }
+        assert_eq!(output.mutations.total().chosen, events);
+    }
+
+    #[test]
+    fn uninhabited_types_stop_with_the_budget() {
+        let options = Options {
+            print_freq: None,
+            budget: Budget::default().with_timeout(std::time::Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let output = simple_map(
+            Polynomials,
+            (0..5).map(|n| (n, n)),
+            None,
+            ty!(A => B),
+            SynthesisParameters::default(),
+            options,
+        );
+
+        assert_eq!(
+            output.err(),
+            Some(MetropolisError::NoStart(StopReason::Deadline))
+        );
+    }
+
+    #[test]
+    fn chain_seeds_do_not_overlap() {
+        let mut seeds = std::collections::HashSet::new();
+        for seed in 0..100 {
+            assert_eq!(chain_seed(seed, 0), seed);
+            for chain in 1..10 {
+                seeds.insert(chain_seed(seed, chain));
+            }
+        }
+
+        assert_eq!(seeds.len(), 900);
+        assert!(seeds.iter().all(|&seed| seed >= 100));
     }
 }
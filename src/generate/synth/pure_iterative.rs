@@ -7,49 +7,60 @@ pub fn pure_iterative<L, O>(
     ty: Type,
     settings: SynthesisParameters,
     options: Options,
-) -> MetropolisOutput<L>
+) -> MetropolisResult<MetropolisOutput<L>>
 where
     L: Language,
     O: TermValue + Clone,
 {
-    let seed_term = Term::val(seed);
+    pure_iterative_observed(lang, seed, examples, start, ty, settings, options, &mut ())
+}
+
+// As `pure_iterative`, reporting each iteration of every chain to `observer`
+#[allow(clippy::too_many_arguments)]
+pub fn pure_iterative_observed<L, O, Obs>(
+    lang: L,
+    seed: O,
+    examples: impl Iterator<Item = O>,
+    start: Option<Term>,
+    ty: Type,
+    settings: SynthesisParameters,
+    options: Options,
+    observer: &mut Obs,
+) -> MetropolisResult<MetropolisOutput<L>>
+where
+    L: Language,
+    O: TermValue + Clone,
+    Obs: Observer,
+{
+    let seed: Value = std::sync::Arc::new(seed);
 
     // If no start term is provided, construct shortest one.
-    let start = start.unwrap_or_else(|| {
-        (1..)
-            .flat_map(|size| search(&lang, vec![], &ty, size))
-            .next()
-            .unwrap()
-            .0
-    });
+    let start = match start {
+        Some(start) => start,
+        None => smallest_term(&lang, &ty, 1, &options.budget)?,
+    };
 
-    let examples = examples.map(std::rc::Rc::new).collect::<Vec<_>>();
+    let examples = examples.map(std::sync::Arc::new).collect::<Vec<_>>();
 
     let num_examples = examples.len();
 
-    let lang_ctxt = lang.context();
+    let lang_ctxt = scoring_context(&lang, &settings);
+    let budget = options.budget.clone();
 
     let int_scorer = |t: &Term| {
         let mut num_correct = 0;
 
-        let mut yielded = seed_term.clone();
+        let program = lang_ctxt.compile(t);
+        let mut yielded = seed.clone();
 
         for o in examples.iter() {
-            let program = term! {
-                [t] [yielded]
-            };
-
-            let evaled = lang_ctxt.evaluate(&program);
+            let output = eval_output(&program, &[yielded], &settings, &budget);
 
-            let Some(output) = evaled.leaf_val() else {
-                unimplemented!("Term `{}` did not evaluate to value.", evaled);
-            };
-
-            if o.is_eq(&output) {
+            if output.is_ok_and(|output| o.is_eq(&output)) {
                 num_correct += 1;
             }
 
-            yielded = Term::Val(o.clone());
+            yielded = o.clone();
         }
 
         num_correct
@@ -67,19 +78,30 @@ where
     };
 
     let start_time = std::time::Instant::now();
-    let (iterations, term, analysis) =
-        metropolis(&lang, &start, &ty, scorer, settings.iterations, options);
+    let mut mutations = MutationStats::default();
+    let mut observe = |event: &Event| {
+        mutations.observe(event);
+        observer.observe(event);
+    };
+    let (chains, iterations, term, analysis) =
+        run_chains(&lang, &start, &ty, scorer, &settings, &options, &mut observe)?;
     let end_time = std::time::Instant::now();
 
     let num_correct = int_scorer(&term);
     let score = scorer(&term);
 
-    MetropolisOutput {
+    Ok(MetropolisOutput {
         term,
         iterations,
+        chains,
         time: end_time.duration_since(start_time).as_secs_f64(),
         num_correct,
         score,
         analysis,
-    }
+        memo: lang_ctxt.memo_stats(),
+        stopped: budget.stop_reason(),
+        mutations,
+        settings,
+        seed: options.seed,
+    })
 }
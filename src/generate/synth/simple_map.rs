@@ -6,40 +6,52 @@ pub fn simple_map<L, I, O>(
     ty: Type,
     settings: SynthesisParameters,
     options: Options,
-) -> MetropolisOutput<L>
+) -> MetropolisResult<MetropolisOutput<L>>
 where
     L: Language,
     I: TermValue + Clone,
     O: TermValue + Clone,
 {
-    let start = start.unwrap_or_else(|| {
-        (2..)
-            .flat_map(|size| search(&lang, vec![], &ty, size))
-            .next()
-            .unwrap()
-            .0
-    });
+    simple_map_observed(lang, examples, start, ty, settings, options, &mut ())
+}
 
-    let examples = examples.map(|(i, o)| (Term::val(i), o)).collect::<Vec<_>>();
+// As `simple_map`, reporting each iteration of every chain to `observer`
+pub fn simple_map_observed<L, I, O, Obs>(
+    lang: L,
+    examples: impl Iterator<Item = (I, O)>,
+    start: Option<Term>,
+    ty: Type,
+    settings: SynthesisParameters,
+    options: Options,
+    observer: &mut Obs,
+) -> MetropolisResult<MetropolisOutput<L>>
+where
+    L: Language,
+    I: TermValue + Clone,
+    O: TermValue + Clone,
+    Obs: Observer,
+{
+    let start = match start {
+        Some(start) => start,
+        None => smallest_term(&lang, &ty, 2, &options.budget)?,
+    };
+
+    let examples = examples
+        .map(|(i, o)| (std::sync::Arc::new(i) as Value, o))
+        .collect::<Vec<_>>();
 
     let num_examples = examples.len();
 
-    let lang_ctxt = lang.context();
+    let lang_ctxt = scoring_context(&lang, &settings);
+    let budget = options.budget.clone();
 
     let int_scorer = |t: &Term| {
         let mut num_correct = 0;
+        let program = lang_ctxt.compile(t);
         for (i, o) in examples.iter() {
-            let program = term! {
-                [t] [i]
-            };
-
-            let evaled = lang_ctxt.evaluate(&program);
+            let output = eval_output(&program, std::slice::from_ref(i), &settings, &budget);
 
-            let Some(output) = evaled.leaf_val() else {
-                unimplemented!("Term `{}` did not evaluate to value.", evaled);
-            };
-
-            if o.is_eq(&output) {
+            if output.is_ok_and(|output| o.is_eq(&output)) {
                 num_correct += 1;
             }
         }
@@ -59,19 +71,30 @@ where
     };
 
     let start_time = std::time::Instant::now();
-    let (iterations, term, analysis) =
-        metropolis(&lang, &start, &ty, scorer, settings.iterations, options);
+    let mut mutations = MutationStats::default();
+    let mut observe = |event: &Event| {
+        mutations.observe(event);
+        observer.observe(event);
+    };
+    let (chains, iterations, term, analysis) =
+        run_chains(&lang, &start, &ty, scorer, &settings, &options, &mut observe)?;
     let end_time = std::time::Instant::now();
 
     let num_correct = int_scorer(&term);
     let score = scorer(&term);
 
-    MetropolisOutput {
+    Ok(MetropolisOutput {
         term,
         iterations,
+        chains,
         time: end_time.duration_since(start_time).as_secs_f64(),
         num_correct,
         score,
         analysis,
-    }
+        memo: lang_ctxt.memo_stats(),
+        stopped: budget.stop_reason(),
+        mutations,
+        settings,
+        seed: options.seed,
+    })
 }
@@ -0,0 +1,282 @@
+// α-equivalence & structural hashing. Bound variables are compared (& hashed)
+// by de Bruijn index, so `\a -> a` and `\b -> b` are the same program, while
+// free variables (e.g. builtins) are compared by name.
+//
+// Values are compared with `TermValue::is_eq`, but hashed only by their type
+// (& by content for integers & booleans, the usual constants). Other values of
+// the same type share a hash, which is slower but never wrong.
+//
+// `Term` & `SharedTerm` share one implementation, through `Syntax`.
+
+use super::*;
+
+use rustc_hash::FxHasher;
+use std::any::Any;
+use std::hash::{Hash, Hasher};
+
+// Key which compares & hashes terms up to α-equivalence
+#[derive(Clone, Debug)]
+pub struct Alpha<T>(pub T);
+
+impl Term {
+    pub fn alpha_eq(&self, other: &Term) -> bool {
+        alpha_eq(self, other)
+    }
+
+    pub fn alpha_hash(&self) -> u64 {
+        alpha_hash(self)
+    }
+}
+
+impl SharedTerm {
+    pub fn alpha_eq(&self, other: &SharedTerm) -> bool {
+        alpha_eq(self, other)
+    }
+
+    // Agrees with `Term::alpha_hash`
+    pub fn alpha_hash(&self) -> u64 {
+        alpha_hash(self)
+    }
+}
+
+// One layer of a term, with its subterms borrowed for the duration of `visit`
+enum Layer<'a, T> {
+    Val(&'a Value),
+    Var(Identifier),
+    Lam(Identifier, &'a T),
+    App(&'a T, &'a T),
+}
+
+trait Syntax: Sized {
+    fn visit<R>(&self, f: impl FnOnce(Layer<'_, Self>) -> R) -> R;
+}
+
+impl Syntax for Term {
+    fn visit<R>(&self, f: impl FnOnce(Layer<'_, Self>) -> R) -> R {
+        use Term::*;
+        match self {
+            Ref(r) => r.borrow().visit(f),
+            Val(v) => f(Layer::Val(v)),
+            Var(v) => f(Layer::Var(*v)),
+            Lam(v, b) => f(Layer::Lam(*v, b)),
+            App(l, r) => f(Layer::App(&l.borrow(), &r.borrow())),
+        }
+    }
+}
+
+impl Syntax for SharedTerm {
+    fn visit<R>(&self, f: impl FnOnce(Layer<'_, Self>) -> R) -> R {
+        use SharedTerm::*;
+        match self {
+            Val(v) => f(Layer::Val(v)),
+            Var(v) => f(Layer::Var(*v)),
+            Lam(v, b) => f(Layer::Lam(*v, b)),
+            App(l, r) => f(Layer::App(l, r)),
+        }
+    }
+}
+
+fn alpha_eq<T: Syntax>(l: &T, r: &T) -> bool {
+    fn eq<T: Syntax>(
+        l: &T,
+        r: &T,
+        l_env: &mut Vec<Identifier>,
+        r_env: &mut Vec<Identifier>,
+    ) -> bool {
+        l.visit(|l| {
+            r.visit(|r| {
+                use Layer::*;
+                match (l, r) {
+                    (Val(a), Val(b)) => a.is_eq(b),
+                    (Var(a), Var(b)) => vars_eq(a, b, l_env, r_env),
+                    (Lam(va, ba), Lam(vb, bb)) => {
+                        l_env.push(va);
+                        r_env.push(vb);
+                        let out = eq(ba, bb, l_env, r_env);
+                        l_env.pop();
+                        r_env.pop();
+                        out
+                    }
+                    (App(ll, lr), App(rl, rr)) => {
+                        eq(ll, rl, l_env, r_env) && eq(lr, rr, l_env, r_env)
+                    }
+                    _ => false,
+                }
+            })
+        })
+    }
+
+    eq(l, r, &mut vec![], &mut vec![])
+}
+
+fn alpha_hash<T: Syntax>(term: &T) -> u64 {
+    fn hash<T: Syntax>(term: &T, env: &mut Vec<Identifier>, state: &mut FxHasher) {
+        term.visit(|layer| match layer {
+            Layer::Val(v) => hash_val(v, state),
+            Layer::Var(v) => hash_var(v, env, state),
+            Layer::Lam(v, b) => {
+                Tag::Lam.hash(state);
+                env.push(v);
+                hash(b, env, state);
+                env.pop();
+            }
+            Layer::App(l, r) => {
+                Tag::App.hash(state);
+                hash(l, env, state);
+                hash(r, env, state);
+            }
+        })
+    }
+
+    let mut state = FxHasher::default();
+    hash(term, &mut vec![], &mut state);
+    state.finish()
+}
+
+#[derive(Hash)]
+enum Tag {
+    Val,
+    Bound,
+    Free,
+    Lam,
+    App,
+}
+
+// De Bruijn index of `v` (if bound)
+fn index(v: Identifier, env: &[Identifier]) -> Option<usize> {
+    env.iter().rev().position(|&u| u == v)
+}
+
+fn vars_eq(a: Identifier, b: Identifier, l_env: &[Identifier], r_env: &[Identifier]) -> bool {
+    match (index(a, l_env), index(b, r_env)) {
+        (Some(i), Some(j)) => i == j,
+        (None, None) => a == b,
+        _ => false,
+    }
+}
+
+// Values equal by `is_eq` have the same type, so always hash the same
+fn hash_val(v: &Value, state: &mut FxHasher) {
+    fn hash_as<T: Any + Hash>(v: &dyn Any, state: &mut FxHasher) -> bool {
+        v.downcast_ref::<T>().map(|v| v.hash(state)).is_some()
+    }
+
+    Tag::Val.hash(state);
+
+    let v = (**v).as_any();
+    v.type_id().hash(state);
+
+    let _ = hash_as::<i32>(v, state)
+        || hash_as::<u32>(v, state)
+        || hash_as::<i64>(v, state)
+        || hash_as::<u64>(v, state)
+        || hash_as::<usize>(v, state)
+        || hash_as::<bool>(v, state);
+}
+
+fn hash_var(v: Identifier, env: &[Identifier], state: &mut FxHasher) {
+    match index(v, env) {
+        Some(i) => {
+            Tag::Bound.hash(state);
+            i.hash(state);
+        }
+        None => {
+            Tag::Free.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
+impl PartialEq for Alpha<Term> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.alpha_eq(&other.0)
+    }
+}
+
+impl Eq for Alpha<Term> {}
+
+impl Hash for Alpha<Term> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.alpha_hash())
+    }
+}
+
+impl PartialEq for Alpha<SharedTerm> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.alpha_eq(&other.0)
+    }
+}
+
+impl Eq for Alpha<SharedTerm> {}
+
+impl Hash for Alpha<SharedTerm> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.alpha_hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(l: Term, r: Term) {
+        assert!(l.alpha_eq(&r), "{} != {}", l, r);
+        assert_eq!(l.alpha_hash(), r.alpha_hash(), "{} & {}", l, r);
+
+        let (l, r) = (l.share(), r.share());
+        assert!(l.alpha_eq(&r));
+        assert_eq!(l.alpha_hash(), r.alpha_hash());
+    }
+
+    fn different(l: Term, r: Term) {
+        assert!(!l.alpha_eq(&r), "{} == {}", l, r);
+        assert!(!l.share().alpha_eq(&r.share()));
+    }
+
+    #[test]
+    fn alpha_equivalent_terms() {
+        same(term!(a -> a), term!(b -> b));
+        same(term!(a b -> plus a b), term!(x y -> plus x y));
+        same(term!(f -> f (x -> f x)), term!(g -> g (y -> g y)));
+        // Shadowing refers to the innermost binder
+        same(term!(a -> a -> a), term!(b -> c -> c));
+        same(term!(x -> plus x [:1]), term!(y -> plus y [:1]));
+    }
+
+    #[test]
+    fn different_binder_structure() {
+        different(term!(a b -> a), term!(a b -> b));
+        different(term!(a -> a -> a), term!(b -> c -> b));
+        different(term!(a -> f a), term!(a -> f b));
+        different(term!(a -> a), term!(a));
+        different(term!(f (x -> x) y), term!(f y (x -> x)));
+    }
+
+    #[test]
+    fn values() {
+        same(term!([:1]), term!([:1]));
+        different(term!([:1]), term!([:2]));
+        // Print the same, but are of different types
+        different(term!([:1i32]), term!([:1u32]));
+        assert_ne!(term!([:1i32]).alpha_hash(), term!([:1u32]).alpha_hash());
+        assert_ne!(term!([:1]).alpha_hash(), term!([:2]).alpha_hash());
+    }
+
+    #[test]
+    fn terms_and_shared_terms_agree() {
+        let terms = [
+            term!(a b -> plus a (b [:3])),
+            term!(f -> f (x -> x) y),
+            term!([:true]),
+        ];
+
+        for term in &terms {
+            assert_eq!(term.alpha_hash(), term.share().alpha_hash(), "{}", term);
+        }
+
+        // Through `Ref` indirections
+        let inner: Thunk = term!(x -> x).into();
+        let with_ref = Term::Lam("y".into(), Term::Ref(inner).into());
+        same(with_ref, term!(y x -> x));
+    }
+}
@@ -0,0 +1,647 @@
+// Compiled programs & environment-based evaluation. A closed term is compiled
+// once into a tree of code in which bound variables are de Bruijn indices into
+// an environment, & can then be run on many inputs without copying its body on
+// each β-step (as graph reduction does). Arguments are passed by need.
+//
+// Builtins still take & return terms, so values crossing that boundary are
+// read back into terms (closures by substituting their environment into their
+// source), & builtin results which are not values are compiled in turn.
+
+use super::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub struct Compiled<'a> {
+    ctxt: &'a Context,
+    code: Code,
+}
+
+enum Code {
+    Val(Value),
+    Local(usize),
+    Global(Identifier, BuiltIn),
+    Free(Identifier),
+    Lam(Rc<Block>),
+    App(Box<Code>, Rc<Block>),
+    // Saturated builtin application (arguments in application order)
+    Call(Identifier, BuiltIn, Vec<Code>),
+    // Closed code, whose value is kept across runs
+    Cached(Box<Code>, RefCell<Option<Rt>>),
+}
+
+// Code together with its source & the names of the variables in scope (innermost
+// last), which are needed to read its closures back into terms
+struct Block {
+    code: Code,
+    src: Term,
+    names: Rc<[Identifier]>,
+}
+
+#[derive(Clone)]
+enum Rt {
+    Val(Value),
+    Closure(Rc<Block>, Env),
+    Partial(Identifier, BuiltIn, Vec<Slot>),
+    Stuck(Term),
+}
+
+type Slot = Rc<RefCell<Lazy>>;
+
+enum Lazy {
+    Pending(Rc<Block>, Env),
+    Done(Rt),
+}
+
+impl From<Rt> for Slot {
+    fn from(rt: Rt) -> Self {
+        Rc::new(RefCell::new(Lazy::Done(rt)))
+    }
+}
+
+#[derive(Clone, Default)]
+struct Env(Option<Rc<(Slot, Env)>>);
+
+impl Env {
+    fn push(&self, slot: Slot) -> Env {
+        Env(Some(Rc::new((slot, self.clone()))))
+    }
+
+    fn get(&self, mut index: usize) -> &Slot {
+        let mut env = self;
+        loop {
+            let (slot, next) = &**env.0.as_ref().expect("Unbound de Bruijn index");
+            if index == 0 {
+                return slot;
+            }
+            index -= 1;
+            env = next;
+        }
+    }
+}
+
+impl Context {
+    pub fn compile(&self, term: &Term) -> Compiled<'_> {
+        Compiled {
+            ctxt: self,
+            code: compile(self, term, &mut vec![], &Rc::from([])),
+        }
+    }
+
+    // Evaluates `term` to weak head normal form (as `evaluate`), but on the
+    // compiled representation, where β-reduction extends an environment rather
+    // than substituting into (& so copying) the body, & cannot capture variables.
+    pub fn evaluate_env(&self, term: &Term) -> Result<Term, EvalError> {
+        self.compile(term).evaluate(&[])
+    }
+
+    pub fn evaluate_env_with_limits(
+        &self,
+        term: &Term,
+        fuel: usize,
+        max_depth: usize,
+    ) -> Result<Term, EvalError> {
+        let _session = LimitSession::start(fuel, max_depth);
+
+        self.evaluate_env(term)
+    }
+}
+
+// `scope` holds the same names as `names`, shared between the blocks compiled in it
+fn compile(
+    ctxt: &Context,
+    term: &Term,
+    names: &mut Vec<Identifier>,
+    scope: &Rc<[Identifier]>,
+) -> Code {
+    use Term::*;
+    match term {
+        Ref(r) => compile(ctxt, &r.borrow(), names, scope),
+        Val(v) => Code::Val(v.clone()),
+        Var(v) => {
+            if let Some(index) = names.iter().rev().position(|u| u == v) {
+                Code::Local(index)
+            } else if let Some(builtin) = ctxt.get(*v) {
+                // Constants are computed once, here (under the limits active while
+                // compiling, if any: see `Limits`)
+                if builtin.n_args == 0 {
+                    if let Ok(Val(v)) = (builtin.func)(ctxt, &[]) {
+                        return Code::Val(v);
+                    }
+                }
+                Code::Global(*v, builtin.clone())
+            } else {
+                Code::Free(*v)
+            }
+        }
+        Lam(v, b) => {
+            names.push(*v);
+            let code = compile(ctxt, b, names, &names.as_slice().into());
+            names.pop();
+
+            Code::Lam(Rc::new(Block {
+                code,
+                src: term.clone(),
+                names: scope.clone(),
+            }))
+        }
+        App(l, r) => {
+            if let Some(code) = compile_call(ctxt, term, names, scope) {
+                return code;
+            }
+
+            let r = r.borrow();
+            let arg = Block {
+                code: share(compile(ctxt, &r, names, scope)),
+                src: r.clone(),
+                names: scope.clone(),
+            };
+
+            Code::App(Box::new(compile(ctxt, &l.borrow(), names, scope)), Rc::new(arg))
+        }
+    }
+}
+
+// Builtins are strict in all their arguments, so when one is applied to enough
+// of them they can be evaluated directly, rather than passed as thunks
+fn compile_call(
+    ctxt: &Context,
+    term: &Term,
+    names: &mut Vec<Identifier>,
+    scope: &Rc<[Identifier]>,
+) -> Option<Code> {
+    let mut head = term.clone();
+    let mut args = vec![];
+    loop {
+        head = match head {
+            Term::Ref(r) => r.borrow().clone(),
+            Term::App(l, r) => {
+                args.push(r.borrow().clone());
+                l.borrow().clone()
+            }
+            _ => break,
+        };
+    }
+    args.reverse();
+
+    let Term::Var(v) = head else {
+        return None;
+    };
+
+    let builtin = ctxt.get(v).filter(|_| !names.contains(&v))?;
+    let n = builtin.n_args;
+    if n == 0 || args.len() < n {
+        return None;
+    }
+
+    let call_args = args[..n]
+        .iter()
+        .map(|arg| share(compile(ctxt, arg, names, scope)))
+        .collect();
+
+    let mut code = Code::Call(v, builtin.clone(), call_args);
+    for arg in &args[n..] {
+        let arg = Block {
+            code: share(compile(ctxt, arg, names, scope)),
+            src: arg.clone(),
+            names: scope.clone(),
+        };
+        code = Code::App(Box::new(code), Rc::new(arg));
+    }
+
+    Some(code)
+}
+
+// Closed applications need only be evaluated once, like the (shared) thunks of
+// a term under graph reduction
+fn share(code: Code) -> Code {
+    fn is_closed(code: &Code, depth: usize) -> bool {
+        match code {
+            Code::Local(i) => *i < depth,
+            Code::Val(_) | Code::Global(_, _) | Code::Free(_) | Code::Cached(_, _) => true,
+            Code::Lam(block) => is_closed(&block.code, depth + 1),
+            Code::App(f, x) => is_closed(f, depth) && is_closed(&x.code, depth),
+            Code::Call(_, _, args) => args.iter().all(|arg| is_closed(arg, depth)),
+        }
+    }
+
+    match code {
+        Code::App(_, _) | Code::Call(_, _, _) if is_closed(&code, 0) => {
+            Code::Cached(Box::new(code), RefCell::new(None))
+        }
+        code => code,
+    }
+}
+
+impl Compiled<'_> {
+    // Applies the program to `args` & evaluates the result to a value
+    pub fn run(&self, args: &[Value]) -> Result<Value, EvalError> {
+        match self.whnf(args)? {
+            Rt::Val(v) => Ok(v),
+            rt => Err(EvalError::Stuck(self.read_back(&rt).to_string())),
+        }
+    }
+
+    // Applies the program to `args` & evaluates the result to weak head normal form
+    pub fn evaluate(&self, args: &[Value]) -> Result<Term, EvalError> {
+        let rt = self.whnf(args)?;
+        Ok(self.read_back(&rt))
+    }
+
+    fn whnf(&self, args: &[Value]) -> Result<Rt, EvalError> {
+        let mut stack = args
+            .iter()
+            .rev()
+            .map(|v| Rt::Val(v.clone()).into())
+            .collect();
+
+        self.eval(&self.code, &Env::default(), &mut stack)
+    }
+
+    // As `Context::evaluate_with_limits`
+    pub fn run_with_limits(
+        &self,
+        args: &[Value],
+        fuel: usize,
+        max_depth: usize,
+    ) -> Result<Value, EvalError> {
+        let _session = LimitSession::start(fuel, max_depth);
+
+        self.run(args)
+    }
+
+    // As `run_with_limits`, also returning the number of reduction steps performed
+    pub fn run_counting_steps(
+        &self,
+        args: &[Value],
+        fuel: usize,
+        max_depth: usize,
+    ) -> (Result<Value, EvalError>, usize) {
+        let session = LimitSession::start(fuel, max_depth);
+
+        let output = self.run(args);
+        let steps = session.steps();
+
+        (output, steps)
+    }
+
+    // Evaluates `code` applied to the arguments on `stack` (last applied first)
+    fn eval(&self, code: &Code, env: &Env, stack: &mut Vec<Slot>) -> Result<Rt, EvalError> {
+        let _depth = Limits::descend()?;
+
+        match code {
+            Code::Val(v) => self.apply(Rt::Val(v.clone()), stack),
+            Code::Local(i) => {
+                let rt = self.force(env.get(*i))?;
+                self.apply(rt, stack)
+            }
+            Code::Global(v, builtin) => {
+                let rt = if builtin.n_args == 0 {
+                    self.call(*v, builtin, vec![])?
+                } else {
+                    Rt::Partial(*v, builtin.clone(), Vec::with_capacity(builtin.n_args))
+                };
+                self.apply(rt, stack)
+            }
+            Code::Free(v) => self.apply(Rt::Stuck(Term::Var(*v)), stack),
+            Code::Lam(block) => match stack.pop() {
+                Some(arg) => {
+                    Limits::step()?;
+                    self.eval(&block.code, &env.push(arg), stack)
+                }
+                None => Ok(Rt::Closure(block.clone(), env.clone())),
+            },
+            Code::App(f, x) => {
+                // Arguments which are already values need no thunk
+                let arg = match &x.code {
+                    Code::Local(i) => env.get(*i).clone(),
+                    Code::Val(v) => Rt::Val(v.clone()).into(),
+                    Code::Global(v, builtin) if builtin.n_args > 0 => {
+                        Rt::Partial(*v, builtin.clone(), vec![]).into()
+                    }
+                    Code::Lam(block) => Rt::Closure(block.clone(), env.clone()).into(),
+                    _ => Rc::new(RefCell::new(Lazy::Pending(x.clone(), env.clone()))),
+                };
+                stack.push(arg);
+                self.eval(f, env, stack)
+            }
+            Code::Call(v, builtin, args) => {
+                let mut thunks = Vec::with_capacity(args.len());
+                for arg in args.iter().rev() {
+                    let rt = self.eval(arg, env, &mut vec![])?;
+                    thunks.push(self.read_back(&rt).into());
+                }
+
+                let rt = self.invoke(*v, builtin, &thunks)?;
+                self.apply(rt, stack)
+            }
+            Code::Cached(code, cache) => {
+                let cached = cache.borrow().clone();
+                let rt = match cached {
+                    Some(rt) => rt,
+                    None => {
+                        let rt = self.eval(code, &Env::default(), &mut vec![])?;
+                        *cache.borrow_mut() = Some(rt.clone());
+                        rt
+                    }
+                };
+                self.apply(rt, stack)
+            }
+        }
+    }
+
+    fn apply(&self, mut rt: Rt, stack: &mut Vec<Slot>) -> Result<Rt, EvalError> {
+        while let Some(arg) = stack.pop() {
+            rt = match rt {
+                Rt::Closure(block, env) => {
+                    Limits::step()?;
+                    return self.eval(&block.code, &env.push(arg), stack);
+                }
+                Rt::Partial(v, builtin, mut args) => {
+                    args.push(arg);
+                    if args.len() == builtin.n_args {
+                        self.call(v, &builtin, args)?
+                    } else {
+                        Rt::Partial(v, builtin, args)
+                    }
+                }
+                rt => {
+                    let head = self.read_back(&rt);
+                    let arg = self.read_back_slot(&arg);
+                    Rt::Stuck(Term::App(head.into(), arg.into()))
+                }
+            };
+        }
+
+        Ok(rt)
+    }
+
+    fn force(&self, slot: &Slot) -> Result<Rt, EvalError> {
+        let (block, env) = match &*slot.borrow() {
+            Lazy::Done(rt) => return Ok(rt.clone()),
+            Lazy::Pending(block, env) => (block.clone(), env.clone()),
+        };
+
+        let rt = self.eval(&block.code, &env, &mut vec![])?;
+        *slot.borrow_mut() = Lazy::Done(rt.clone());
+        Ok(rt)
+    }
+
+    fn call(&self, v: Identifier, builtin: &BuiltIn, args: Vec<Slot>) -> Result<Rt, EvalError> {
+        // Builtins take their arguments last first
+        let mut thunks = Vec::with_capacity(args.len());
+        for arg in args.iter().rev() {
+            let rt = self.force(arg)?;
+            thunks.push(self.read_back(&rt).into());
+        }
+
+        self.invoke(v, builtin, &thunks)
+    }
+
+    fn invoke(&self, v: Identifier, builtin: &BuiltIn, args: &[Thunk]) -> Result<Rt, EvalError> {
+        Limits::step()?;
+
+        let out = self.ctxt.call_builtin(v, builtin, args)?;
+        self.load(&out)
+    }
+
+    // Converts a term produced by a builtin into a runtime value
+    fn load(&self, term: &Term) -> Result<Rt, EvalError> {
+        match term {
+            Term::Ref(r) => self.load(&r.borrow()),
+            Term::Val(v) => Ok(Rt::Val(v.clone())),
+            _ => {
+                let code = compile(self.ctxt, term, &mut vec![], &Rc::from([]));
+                self.eval(&code, &Env::default(), &mut vec![])
+            }
+        }
+    }
+
+    fn read_back(&self, rt: &Rt) -> Term {
+        match rt {
+            Rt::Val(v) => Term::Val(v.clone()),
+            Rt::Closure(block, env) => self.read_back_block(block, env),
+            Rt::Partial(v, _, args) => args.iter().fold(Term::Var(*v), |head, arg| {
+                Term::App(head.into(), self.read_back_slot(arg).into())
+            }),
+            Rt::Stuck(term) => term.clone(),
+        }
+    }
+
+    fn read_back_slot(&self, slot: &Slot) -> Term {
+        match &*slot.borrow() {
+            Lazy::Done(rt) => self.read_back(rt),
+            Lazy::Pending(block, env) => self.read_back_block(block, env),
+        }
+    }
+
+    // Substitutes the (closed) read back environment into the block's source
+    fn read_back_block(&self, block: &Block, env: &Env) -> Term {
+        let mut term = block.src.clone();
+        let mut seen = vec![];
+        for (i, name) in block.names.iter().rev().enumerate() {
+            if seen.contains(name) {
+                continue;
+            }
+            seen.push(*name);
+
+            let val = self.read_back_slot(env.get(i));
+            term = term.substitute(*name, &mut || val.clone());
+        }
+        term
+    }
+}
+
+// The environment-based evaluator (`Context::evaluate_env`) must agree with
+// graph reduction (`Context::evaluate`) on the programs of every bundled
+// language, applied to a handful of inputs
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::*;
+
+    const FUEL: usize = 100_000;
+    const MAX_DEPTH: usize = 2_000;
+
+    fn ints(n: i32) -> Vec<Vec<Term>> {
+        (0..n).map(|i| vec![Term::val(i)]).collect()
+    }
+
+    fn nats(n: u32) -> Vec<Vec<Term>> {
+        (0..n).map(|i| vec![Term::val(i)]).collect()
+    }
+
+    fn int_pairs() -> Vec<Vec<Term>> {
+        (0..4)
+            .flat_map(|i| (0..4).map(move |j| vec![Term::val(i), Term::val(j)]))
+            .collect()
+    }
+
+    // Runs every program of type `ty` (up to `max_size`) on each of `inputs`
+    fn check<L: Language>(lang: &L, ty: &Type, inputs: &[Vec<Term>], max_size: usize) {
+        let ctxt = lang.context();
+        let mut programs = 0;
+
+        for size in 1..=max_size {
+            for (term, _) in search(lang, vec![], ty, size) {
+                programs += 1;
+                for args in inputs {
+                    let program = args.iter().fold(term.clone(), |program, arg| {
+                        Term::App(program.into(), arg.clone().into())
+                    });
+
+                    assert_agree(&ctxt, &program);
+                }
+            }
+        }
+
+        assert!(programs > 0, "no programs of type {}", ty);
+    }
+
+    fn assert_agree(ctxt: &Context, program: &Term) {
+        let graph = ctxt.evaluate_with_limits(program, FUEL, MAX_DEPTH);
+        let env = ctxt.evaluate_env_with_limits(program, FUEL, MAX_DEPTH);
+
+        let agree = match (&graph, &env) {
+            // The evaluators count steps differently, so may run out at different points
+            (Err(EvalError::OutOfFuel | EvalError::TooDeep), _)
+            | (_, Err(EvalError::OutOfFuel | EvalError::TooDeep)) => true,
+            (Err(a), Err(b)) => a == b,
+            (Ok(a), Ok(b)) => match (a.leaf_val(), b.leaf_val()) {
+                (Some(a), Some(b)) => a.is_eq(&b),
+                (None, None) => {
+                    let a = ctxt.normalize_with_limits(a, FUEL, MAX_DEPTH);
+                    let b = ctxt.normalize_with_limits(b, FUEL, MAX_DEPTH);
+                    match (a, b) {
+                        (Ok(a), Ok(b)) => a.alpha_eq(&b),
+                        _ => true,
+                    }
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+
+        assert!(
+            agree,
+            "Evaluators disagree on {}: {:?} vs {:?}",
+            program,
+            graph.map(|t| t.to_string()),
+            env.map(|t| t.to_string()),
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let free = |v: &str| Term::Var(Identifier::intern(v));
+
+        check(
+            &Empty,
+            &ty!((A => A) => A => A),
+            &[vec![free("f"), free("x")]],
+            9,
+        );
+        check(
+            &Empty,
+            &ty!((((A => B) => B) => B) => A => B),
+            &[vec![]],
+            10,
+        );
+    }
+
+    #[test]
+    fn polynomials() {
+        check(&Polynomials, &ty!(N => N => N), &int_pairs(), 11);
+    }
+
+    #[test]
+    fn opaque() {
+        check(&Opaque, &ty!(N => N => N), &int_pairs(), 9);
+    }
+
+    #[test]
+    fn fib_lang() {
+        check(&FibLang, &ty!(N => N), &ints(8), 13);
+        check(&FibLang, &ty!(N => N => N), &int_pairs(), 9);
+    }
+
+    #[test]
+    fn cond_poly() {
+        check(&CondPolyLang, &ty!(Poly => N), &ints(6), 12);
+    }
+
+    #[test]
+    fn num_logic() {
+        check(&NumLogic::new(2), &ty!(Var => Num), &nats(10), 14);
+        check(&NumLogic::new(2), &ty!(Var => Bool), &nats(10), 14);
+    }
+
+    #[test]
+    fn dumb_num() {
+        check(&DumbNum, &ty!(Var => Num), &nats(10), 12);
+    }
+
+    #[test]
+    fn logic_lang() {
+        check(&LogicLang::new(2), &ty!(N => Bool), &nats(10), 14);
+    }
+
+    // Higher-order & structured programs, which search does not reach in the
+    // bundled languages
+    #[test]
+    fn data() {
+        let mut ctxt = Polynomials.context();
+        ctxt.insert(&product_builtins());
+        ctxt.insert(&sum_builtins());
+        ctxt.insert(&list_builtins());
+
+        let programs = [
+            term!(n -> fold plus zero (cons n (cons one (cons n nil)))),
+            term!(n -> fold (x acc -> mult x acc) one (cons n (cons n nil))),
+            term!(n -> either (inl n) (x -> plus x one) (y -> y)),
+            term!(n -> either (inr n) (x -> plus x one) (y -> mult y y)),
+            term!(n -> fst (pair (plus n n) (snd (pair n n)))),
+            term!(n -> (f -> f (f n)) (plus one)),
+            term!(n -> (f -> fold f n (cons one nil)) (x y -> plus x y)),
+            term!(n -> (x y -> y) (fst n) n),
+            term!(n -> (x -> x x) (y -> n)),
+            term!(n -> (x y -> x) n),
+            term!(n -> pair n),
+            term!(n -> cons (plus n one) nil),
+            term!(n -> (x y -> x y) (z -> z)),
+        ];
+
+        for program in &programs {
+            for n in 0..5 {
+                assert_agree(&ctxt, &term!([program][Term::val(n)]));
+            }
+        }
+    }
+
+    // Unlike `assert_agree`, the evaluators must fail in the same way here
+    #[test]
+    fn errors() {
+        let ctxt = Opaque.context();
+        let both = |term: &Term, fuel, max_depth| {
+            (
+                ctxt.evaluate_with_limits(term, fuel, max_depth),
+                ctxt.evaluate_env_with_limits(term, fuel, max_depth),
+            )
+        };
+
+        let omega = term!((x -> x x) (x -> x x));
+        let (graph, env) = both(&omega, 100, MAX_DEPTH);
+        assert_eq!(graph.unwrap_err(), EvalError::OutOfFuel);
+        assert_eq!(env.unwrap_err(), EvalError::OutOfFuel);
+
+        // Each argument must be evaluated before the enclosing `plus`
+        let deep = (0..50).fold(term!(one), |acc, _| term!(plus one [acc]));
+        let (graph, env) = both(&deep, FUEL, 10);
+        assert_eq!(graph.unwrap_err(), EvalError::TooDeep);
+        assert_eq!(env.unwrap_err(), EvalError::TooDeep);
+
+        let overflow = term!(plus [:i32::MAX] one);
+        let (graph, env) = both(&overflow, FUEL, MAX_DEPTH);
+        assert_eq!(graph.unwrap_err(), EvalError::Overflow);
+        assert_eq!(env.unwrap_err(), EvalError::Overflow);
+    }
+}
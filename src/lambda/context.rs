@@ -4,20 +4,53 @@
 
 use super::*;
 use rustc_hash::FxHashMap as HashMap;
-use std::{fmt::Debug, rc::Rc};
+use std::{cell::Cell, fmt::Debug, rc::Rc, sync::Arc};
 
 #[derive(Clone)]
 pub struct Context {
     defs: HashMap<Identifier, BuiltIn>,
+    strategy: Strategy,
+    // Set by `normalize`
+    pub(super) lenient: bool,
+    pub(super) memo: Option<Arc<Memo>>,
+}
+
+// How arguments are passed when a λ-abstraction is applied. Builtins always
+// receive their arguments evaluated, whatever the strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strategy {
+    // Arguments are evaluated when first needed, & the result shared
+    #[default]
+    CallByNeed,
+    // Arguments are evaluated (to weak head normal form) before substitution
+    CallByValue,
+    // Arguments are copied unevaluated into each occurrence (call-by-name)
+    NormalOrder,
 }
 
 impl Context {
     pub fn new(defs: impl IntoIterator<Item = (Identifier, BuiltIn)>) -> Self {
         Self {
             defs: HashMap::from_iter(defs),
+            strategy: Strategy::default(),
+            lenient: false,
+            memo: None,
         }
     }
 
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
     pub fn insert(&mut self, defs: &[(Identifier, BuiltIn)]) {
         for (ident, def) in defs {
             self.defs.insert(*ident, def.clone());
@@ -42,50 +75,58 @@ impl Context {
         vgen
     }
 
-    pub fn vars_producing<'a>(
-        &'a self,
-        ty: &'a Type,
-    ) -> impl Iterator<Item = (Identifier, Rc<Type>)> + 'a {
-        fn produces(ty: &Type, target: &Type) -> bool {
-            let ret_ty_produces = match ty {
-                Type::Fun(_, r) => produces(r, target),
-                _ => false,
-            };
+    // Evaluates `term` (as `evaluate`), but gives up once `fuel` reduction steps
+    // have been performed or the evaluator recurses more than `max_depth` times.
+    // Nested evaluations started by builtins draw on the same limits (see `Limits`).
+    pub fn evaluate_with_limits(
+        &self,
+        term: &Term,
+        fuel: usize,
+        max_depth: usize,
+    ) -> Result<Term, EvalError> {
+        let _session = LimitSession::start(fuel, max_depth);
 
-            ret_ty_produces || target == ty
-        }
+        self.evaluate(term)
+    }
 
-        self.defs
-            .iter()
-            .filter_map(move |(v, BuiltIn { ty: t, .. })| {
-                if produces(t, ty) {
-                    Some((*v, t.clone()))
-                } else {
-                    None
-                }
-            })
+    // As `evaluate_with_limits`, but for `normalize`
+    pub fn normalize_with_limits(
+        &self,
+        term: &Term,
+        fuel: usize,
+        max_depth: usize,
+    ) -> Result<Term, EvalError> {
+        let _session = LimitSession::start(fuel, max_depth);
+
+        self.normalize(term)
     }
 
-    pub fn evaluate(&self, term: &Term) -> Term {
+    pub fn evaluate(&self, term: &Term) -> Result<Term, EvalError> {
         let mut thunk: Thunk = term.clone().into();
-        self.evaluate_thunk(&mut thunk);
-        Rc::unwrap_or_clone(thunk).into_inner()
+        self.evaluate_thunk(&mut thunk)?;
+        Ok(Rc::unwrap_or_clone(thunk).into_inner())
     }
 
-    pub fn evaluate_thunk(&self, thunk: &mut Thunk) {
+    pub fn evaluate_thunk(&self, thunk: &mut Thunk) -> Result<(), EvalError> {
         use Term::*;
+        let _depth = Limits::descend()?;
+
         let mut borrow = (**thunk).borrow_mut();
         let term = &mut *borrow;
         match term {
-            Val(_) | Lam(_, _) => (),
+            Val(_) | Lam(_, _) => Ok(()),
             Var(v) => {
                 if let Some(BuiltIn {
                     func, n_args: 0, ..
                 }) = self.get(*v)
                 {
-                    *term = func(self, &mut []).unwrap();
+                    Limits::step()?;
+
+                    *term = func(self, &mut [])?;
                     drop(borrow);
                     self.evaluate_thunk(thunk)
+                } else {
+                    Ok(())
                 }
             }
             Ref(next) => {
@@ -95,33 +136,38 @@ impl Context {
                 self.evaluate_thunk(thunk)
             }
             App(_, _) => {
-                self.collapse_spine(term, 0);
+                self.collapse_spine(term, 0)?;
+                Ok(())
             }
         }
     }
 
-    fn collapse_spine(&self, root: &mut Term, depth: usize) -> SpineCollapse {
+    fn collapse_spine(&self, root: &mut Term, depth: usize) -> Result<SpineCollapse, EvalError> {
         use SpineCollapse::*;
         use Term::*;
+        let _depth = Limits::descend()?;
+
         match root {
             Ref(thunk) => self.collapse_spine(&mut thunk.borrow_mut(), depth),
-            Val(_) | Lam(_, _) => Whnf,
+            Val(_) | Lam(_, _) => Ok(Whnf),
             Var(v) => match self.get(*v) {
                 Some(BuiltIn {
                     func, n_args: 0, ..
                 }) => {
-                    *root = func(self, &mut []).unwrap();
+                    Limits::step()?;
+
+                    *root = func(self, &mut [])?;
                     self.collapse_spine(root, depth)
                 }
                 Some(blt) if blt.n_args <= depth => {
-                    Exec(blt.clone(), Vec::with_capacity(blt.n_args))
+                    Ok(Exec(*v, blt.clone(), Vec::with_capacity(blt.n_args)))
                 }
-                _ => Whnf,
+                _ => Ok(Whnf),
             },
             App(l, r) => {
                 let mut borr = l.borrow_mut();
-                match self.collapse_spine(&mut borr, depth + 1) {
-                    Exec(builtin, mut args) => {
+                match self.collapse_spine(&mut borr, depth + 1)? {
+                    Exec(ident, builtin, mut args) => {
                         drop(borr);
                         let argc = builtin.n_args;
 
@@ -131,34 +177,55 @@ impl Context {
                             args.reverse();
 
                             for arg in &mut args[..] {
-                                self.evaluate_thunk(arg);
+                                self.evaluate_thunk(arg)?;
                             }
 
-                            let func = &*builtin.func;
+                            Limits::step()?;
 
-                            if let Some(term) = func(self, &mut args[..]) {
-                                *root = term;
-                                return self.collapse_spine(root, depth);
-                            }
+                            *root = match self.call_builtin(ident, &builtin, &args) {
+                                Ok(out) => out,
+                                Err(err @ (EvalError::OutOfFuel | EvalError::TooDeep)) => {
+                                    return Err(err)
+                                }
+                                // While normalizing, builtins which cannot run are left in place
+                                Err(_) if self.lenient => return Ok(Whnf),
+                                Err(err) => return Err(err),
+                            };
+                            return self.collapse_spine(root, depth);
                         }
 
-                        Exec(builtin, args)
+                        Ok(Exec(ident, builtin, args))
                     }
                     Whnf => {
                         drop(borr);
-                        let borr = (**l).borrow();
-                        if let Lam(_, _) = *borr {
-                            let Lam(v, b) = borr.clone() else {
-                                unreachable!()
-                            };
 
-                            drop(borr);
+                        // The head may sit behind `Ref`s (e.g. a substituted argument)
+                        fn lam_of(term: &Term) -> Option<(Identifier, Rc<Term>)> {
+                            match term {
+                                Ref(r) => lam_of(&r.borrow()),
+                                Lam(v, b) => Some((*v, b.clone())),
+                                _ => None,
+                            }
+                        }
+
+                        let lam = lam_of(&l.borrow());
+                        if let Some((v, b)) = lam {
+                            Limits::step()?;
 
-                            *root = b.instantiate_var(v, r);
+                            *root = match self.strategy {
+                                Strategy::CallByNeed => b.instantiate_var(v, r),
+                                Strategy::CallByValue => {
+                                    let mut arg = r.clone();
+                                    self.evaluate_thunk(&mut arg)?;
+                                    b.instantiate_var(v, &arg)
+                                }
+                                Strategy::NormalOrder => {
+                                    b.substitute(v, &mut || r.borrow().deep_clone())
+                                }
+                            };
                             self.collapse_spine(root, depth)
                         } else {
-                            drop(borr);
-                            Whnf
+                            Ok(Whnf)
                         }
                     }
                 }
@@ -167,9 +234,139 @@ impl Context {
     }
 }
 
+// Resources remaining to the limited evaluation running on this thread (if any).
+//
+// Limits are per-thread state rather than arguments, so that builtins (which
+// only receive the `Context`) are bounded too. The rules are:
+// - A limited evaluation (`evaluate_with_limits`, `Compiled::run_with_limits`,
+//   ...) installs a session for its duration, restoring the previous one after.
+// - Unlimited evaluations started during a session (by builtins calling
+//   `evaluate`, by `normalize`, by compiling a builtin's result, ...) draw on
+//   that session's fuel & depth. Outside any session they are unbounded.
+// - A nested session is bounded by the enclosing one: it gets at most the fuel
+//   & depth remaining to it, & the steps it takes are charged to it as well.
+// - Sessions do not follow work onto other threads.
+#[derive(Clone, Copy)]
+pub(super) struct Limits {
+    pub(super) fuel: usize,
+    pub(super) max_depth: usize,
+    pub(super) depth: usize,
+}
+
+thread_local! {
+    static LIMITS: Cell<Option<Limits>> = const { Cell::new(None) };
+}
+
+impl Limits {
+    fn update<T>(f: impl FnOnce(&mut Limits) -> T) -> Option<T> {
+        LIMITS.with(|cell| {
+            let mut limits = cell.get()?;
+            let out = f(&mut limits);
+            cell.set(Some(limits));
+            Some(out)
+        })
+    }
+
+    // Fuel left to the limited evaluation running on this thread (if any)
+    pub(super) fn remaining_fuel() -> Option<usize> {
+        Self::update(|limits| limits.fuel)
+    }
+
+    // Charges one reduction step
+    pub(super) fn step() -> Result<(), EvalError> {
+        Self::update(|limits| {
+            if limits.fuel == 0 {
+                return Err(EvalError::OutOfFuel);
+            }
+            limits.fuel -= 1;
+            Ok(())
+        })
+        .unwrap_or(Ok(()))
+    }
+
+    // Charges `steps` reduction steps at once, running out if fewer remain
+    pub(super) fn charge(steps: usize) -> Result<(), EvalError> {
+        Self::update(|limits| {
+            if limits.fuel < steps {
+                limits.fuel = 0;
+                return Err(EvalError::OutOfFuel);
+            }
+            limits.fuel -= steps;
+            Ok(())
+        })
+        .unwrap_or(Ok(()))
+    }
+
+    // Enters one level of recursion, which lasts until the guard is dropped
+    pub(super) fn descend() -> Result<DepthGuard, EvalError> {
+        Self::update(|limits| {
+            if limits.depth >= limits.max_depth {
+                return Err(EvalError::TooDeep);
+            }
+            limits.depth += 1;
+            Ok(())
+        })
+        .unwrap_or(Ok(()))
+        .map(|_| DepthGuard)
+    }
+}
+
+pub(super) struct DepthGuard;
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        Limits::update(|limits| limits.depth -= 1);
+    }
+}
+
+// Installs limits for the current thread, restoring the previous ones when dropped
+pub(super) struct LimitSession {
+    outer: Option<Limits>,
+    fuel: usize, // At the start of the session
+}
+
+impl LimitSession {
+    pub(super) fn start(fuel: usize, max_depth: usize) -> Self {
+        let outer = LIMITS.get();
+        let limits = match outer {
+            None => Limits {
+                fuel,
+                max_depth,
+                depth: 0,
+            },
+            Some(outer) => Limits {
+                fuel: fuel.min(outer.fuel),
+                max_depth: outer.depth.saturating_add(max_depth).min(outer.max_depth),
+                depth: outer.depth,
+            },
+        };
+        LIMITS.set(Some(limits));
+
+        Self {
+            outer,
+            fuel: limits.fuel,
+        }
+    }
+
+    // Reduction steps performed so far in this session
+    pub(super) fn steps(&self) -> usize {
+        self.fuel - Limits::remaining_fuel().unwrap_or(self.fuel)
+    }
+}
+
+impl Drop for LimitSession {
+    fn drop(&mut self) {
+        let steps = self.steps();
+        LIMITS.set(self.outer.map(|mut outer| {
+            outer.fuel -= steps;
+            outer
+        }));
+    }
+}
+
 enum SpineCollapse {
     Whnf,
-    Exec(BuiltIn, Vec<Thunk>),
+    Exec(Identifier, BuiltIn, Vec<Thunk>),
 }
 
 impl Debug for Context {
@@ -177,3 +374,87 @@ impl Debug for Context {
         write!(f, "{:?}", self.defs.keys().collect::<Vec<_>>())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `spin` diverges through an unlimited nested evaluation, & `spin_limited`
+    // through a nested session with far more resources than its caller has
+    fn context() -> Context {
+        let spin = builtin!(
+            N => N
+            ctxt |_n| => ctxt.evaluate(&term!((x -> x x) (x -> x x)))?
+        );
+
+        let spin_limited = builtin!(
+            N => N
+            ctxt |_n| => {
+                let omega = term!((x -> x x) (x -> x x));
+                ctxt.evaluate_with_limits(&omega, usize::MAX, usize::MAX)?
+            }
+        );
+
+        let succ = builtin!(
+            N => N
+            |n| => Term::val(n.get::<i32>()? + 1)
+        );
+
+        let succ2 = builtin!(
+            N => N
+            ctxt |n| => ctxt.evaluate(&term!(succ (succ [n.clone()])))?
+        );
+
+        let succ2_limited = builtin!(
+            N => N
+            ctxt |n| => ctxt.evaluate_with_limits(&term!(succ (succ [n.clone()])), 1000, 1000)?
+        );
+
+        context! { spin, spin_limited, succ, succ2, succ2_limited }
+    }
+
+    #[test]
+    fn nested_evaluations_share_limits() {
+        let ctxt = context();
+
+        let out = ctxt.evaluate_with_limits(&term!(spin [:0]), 100, 100_000);
+        assert_eq!(out.unwrap_err(), EvalError::OutOfFuel);
+    }
+
+    #[test]
+    fn nested_sessions_are_bounded() {
+        let ctxt = context();
+
+        let out = ctxt.evaluate_with_limits(&term!(spin_limited [:0]), 100, 100_000);
+        assert_eq!(out.unwrap_err(), EvalError::OutOfFuel);
+
+        let out = ctxt.evaluate_with_limits(&term!(spin_limited [:0]), usize::MAX, 50);
+        assert_eq!(out.unwrap_err(), EvalError::TooDeep);
+    }
+
+    #[test]
+    fn nested_sessions_charge_their_steps() {
+        let ctxt = context();
+
+        let steps = |f| {
+            let program = ctxt.compile(&term!(n -> [Term::Var(f)] n));
+            let (out, steps) = program.run_counting_steps(&[Arc::new(1i32)], 1000, 1000);
+            assert_eq!(out.unwrap().to_string(), "3");
+            steps
+        };
+
+        assert_eq!(steps("succ2".into()), steps("succ2_limited".into()));
+    }
+
+    #[test]
+    fn sessions_are_restored() {
+        let ctxt = context();
+
+        let out = ctxt.evaluate_with_limits(&term!(spin [:0]), 10, 100_000);
+        assert_eq!(out.unwrap_err(), EvalError::OutOfFuel);
+        assert!(LIMITS.get().is_none());
+
+        let out = ctxt.evaluate(&term!(succ [:1])).unwrap();
+        assert_eq!(out.get::<i32>(), Ok(2));
+    }
+}
@@ -0,0 +1,210 @@
+// Structured data. Values of product, sum & list types are built & taken apart
+// by the polymorphic builtins below, which a language adds to its context
+// (e.g. `ctxt.insert(&list_builtins())`) to have search enumerate them like
+// any other builtin. Builtins receive their arguments evaluated, so components
+// are stored in weak head normal form.
+//
+// These are builtins rather than new kinds of term (or search node) so that the
+// evaluator, compiler, normalization, α-equivalence & sharing of terms are all
+// unchanged: only types & unification know about data. Search instantiates the
+// builtins' type schemes as needed, so `fold` is found wherever a fold fits.
+// No bundled language uses them yet, as doing so changes which terms it searches.
+
+use super::*;
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pair(pub SharedTerm, pub SharedTerm);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Either {
+    Left(SharedTerm),
+    Right(SharedTerm),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct List(pub Vec<SharedTerm>);
+
+pub fn product_builtins() -> Vec<(Identifier, BuiltIn)> {
+    let pair = builtin! {
+        forall a b.
+        a => b => (a, b)
+        |x, y| => Term::val(Pair(x.share(), y.share()))
+    };
+
+    let fst = builtin! {
+        forall a b.
+        (a, b) => a
+        |p| => p.get::<Pair>()?.0.to_term()
+    };
+
+    let snd = builtin! {
+        forall a b.
+        (a, b) => b
+        |p| => p.get::<Pair>()?.1.to_term()
+    };
+
+    vec![
+        ("pair".into(), pair),
+        ("fst".into(), fst),
+        ("snd".into(), snd),
+    ]
+}
+
+pub fn sum_builtins() -> Vec<(Identifier, BuiltIn)> {
+    let inl = builtin! {
+        forall a b.
+        a => (a | b)
+        |x| => Term::val(Either::Left(x.share()))
+    };
+
+    let inr = builtin! {
+        forall a b.
+        b => (a | b)
+        |x| => Term::val(Either::Right(x.share()))
+    };
+
+    let either = builtin! {
+        forall a b c.
+        (a | b) => (a => c) => (b => c) => c
+        |s, l, r| => match s.get::<Either>()? {
+            Either::Left(x) => {
+                let x = x.to_term();
+                term!([l] [x])
+            }
+            Either::Right(x) => {
+                let x = x.to_term();
+                term!([r] [x])
+            }
+        }
+    };
+
+    vec![
+        ("inl".into(), inl),
+        ("inr".into(), inr),
+        ("either".into(), either),
+    ]
+}
+
+pub fn list_builtins() -> Vec<(Identifier, BuiltIn)> {
+    let nil = builtin! {
+        forall a.
+        (List a)
+        | | => Term::val(List(vec![]))
+    };
+
+    let cons = builtin! {
+        forall a.
+        a => (List a) => (List a)
+        |x, xs| => {
+            let mut xs = xs.get::<List>()?.0;
+            xs.insert(0, x.share());
+            Term::val(List(xs))
+        }
+    };
+
+    // Right fold
+    let fold = builtin! {
+        forall a b.
+        (a => b => b) => b => (List a) => b
+        |f, z, xs| => {
+            xs.get::<List>()?.0.iter().rev().fold(z.clone(), |acc, x| {
+                let x = x.to_term();
+                term!([f] [x] [acc])
+            })
+        }
+    };
+
+    vec![
+        ("nil".into(), nil),
+        ("cons".into(), cons),
+        ("fold".into(), fold),
+    ]
+}
+
+impl Display for Pair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.0, self.1)
+    }
+}
+
+impl Display for Either {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Either::Left(x) => write!(f, "inl({})", x),
+            Either::Right(x) => write!(f, "inr({})", x),
+        }
+    }
+}
+
+impl Display for List {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (i, x) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", x)?;
+        }
+        write!(f, "]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::Opaque;
+
+    // Opaque, with lists
+    #[derive(Clone, Debug)]
+    struct Lists;
+
+    impl Language for Lists {
+        type Semantics = OpaqueSemantics;
+
+        fn context(&self) -> Context {
+            let mut ctxt = Opaque.context();
+            ctxt.insert(&list_builtins());
+            ctxt
+        }
+    }
+
+    fn eval(term: Term) -> i32 {
+        let mut ctxt = Lists.context();
+        ctxt.insert(&product_builtins());
+        ctxt.insert(&sum_builtins());
+
+        let out = ctxt.evaluate(&term).unwrap();
+        out.get::<i32>().unwrap()
+    }
+
+    #[test]
+    fn builtins_evaluate() {
+        assert_eq!(eval(term!(fst (pair 1 2))), 1);
+        assert_eq!(eval(term!(snd (pair 1 2))), 2);
+        assert_eq!(eval(term!(either (inl 3) (x -> x) (x -> 0))), 3);
+        assert_eq!(eval(term!(either (inr 3) (x -> 0) (x -> plus x x))), 6);
+        assert_eq!(eval(term!(fold plus 0 (cons 1 (cons 2 (cons 3 nil))))), 6);
+        assert_eq!(eval(term!(fold (x acc -> acc) 5 nil)), 5);
+    }
+
+    #[test]
+    fn builtins_are_enumerated() {
+        let ctxt = Lists.context();
+        let targ = ty!((List N) => N);
+
+        let terms: Vec<_> = search(&Lists, vec![], &targ, 8)
+            .map(|(term, _)| term)
+            .collect();
+
+        for term in &terms {
+            assert!(check(&ctxt, &vec![], term, &targ).is_ok(), "{}", term);
+        }
+
+        let sum = term!(xs -> fold plus zero xs);
+        assert!(terms.iter().any(|term| term.alpha_eq(&sum)));
+
+        assert_eq!(count_terms(&Lists, vec![], &targ, 8), terms.len() as u128);
+    }
+}
@@ -1,11 +1,23 @@
 use super::*;
-use std::rc::Rc;
+use std::sync::Arc;
 
-type BuiltInFunc = Rc<dyn Fn(&Context, &[Thunk]) -> Option<Term>>;
+type BuiltInFunc = Arc<dyn Fn(&Context, &[Thunk]) -> Result<Term, EvalError> + Send + Sync>;
 
 #[derive(Clone)]
 pub struct BuiltIn {
     pub n_args: usize,
     pub func: BuiltInFunc,
-    pub ty: Rc<Type>,
+    pub ty: Arc<Type>,
+    // Quantified type variables of `ty` (empty unless the builtin is polymorphic)
+    pub params: Arc<[Identifier]>,
+    // Whether calls may be memoized (see `memo.rs`). Only worth it for builtins
+    // which cost far more than copying & hashing their arguments.
+    pub memoize: bool,
+}
+
+impl BuiltIn {
+    pub fn memoized(mut self) -> Self {
+        self.memoize = true;
+        self
+    }
 }
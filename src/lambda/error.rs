@@ -0,0 +1,32 @@
+use std::fmt::{Display, Formatter, Result};
+
+// Reasons evaluation (or extracting a value from its result) can fail.
+// Terms are stored by their printed form so errors can be sent between threads.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvalError {
+    TypeMismatch {
+        expected: &'static str,
+        found: String,
+    },
+    Stuck(String), // Term was expected to reduce to a value, but did not
+    Overflow,
+    OutOfFuel,
+    TooDeep, // Exceeded maximum recursion depth
+}
+
+impl Display for EvalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        use EvalError::*;
+        match self {
+            TypeMismatch { expected, found } => {
+                write!(f, "expected value of type `{}`, found `{}`", expected, found)
+            }
+            Stuck(term) => write!(f, "term `{}` did not evaluate to a value", term),
+            Overflow => write!(f, "arithmetic overflow"),
+            OutOfFuel => write!(f, "ran out of fuel"),
+            TooDeep => write!(f, "exceeded maximum recursion depth"),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
@@ -0,0 +1,182 @@
+// Opt-in memoization of builtin calls. Builtins are pure functions of their
+// (evaluated) arguments, so once a context has a memo table, the values that
+// calls on closed arguments return are recorded, keyed on the builtin & the
+// arguments up to α-equivalence, & reused by every later call (on any clone
+// of the context, so across examples & candidates alike).
+//
+// Only builtins marked with `BuiltIn::memoized` are memoized, as each call
+// copies & hashes its arguments: for cheap builtins (e.g. `plus`) that costs
+// more than the call. Only calls returning values are recorded, & failed calls
+// never are (as they may only have run out of fuel). The table is unbounded
+// until cleared.
+//
+// A hit is charged the reduction steps the recorded call took, so memoization
+// never changes whether an evaluation runs out of fuel. Depth is not recorded:
+// a hit may succeed where repeating the call would have been too deep.
+
+use super::*;
+use rustc_hash::FxHashMap as HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+type MemoKey = (Identifier, Vec<Alpha<SharedTerm>>);
+
+#[derive(Default)]
+pub(super) struct Memo {
+    table: Mutex<HashMap<MemoKey, (Value, usize)>>, // Value returned & steps taken
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub entries: usize,
+}
+
+impl Context {
+    pub fn with_memo(mut self) -> Self {
+        self.memo = Some(Arc::default());
+        self
+    }
+
+    // `None` unless memoization is enabled
+    pub fn memo_stats(&self) -> Option<MemoStats> {
+        let memo = self.memo.as_ref()?;
+        Some(MemoStats {
+            hits: memo.hits.load(Ordering::Relaxed),
+            misses: memo.misses.load(Ordering::Relaxed),
+            entries: memo.table.lock().unwrap().len(),
+        })
+    }
+
+    pub fn clear_memo(&self) {
+        if let Some(memo) = &self.memo {
+            memo.table.lock().unwrap().clear();
+            memo.hits.store(0, Ordering::Relaxed);
+            memo.misses.store(0, Ordering::Relaxed);
+        }
+    }
+
+    // Calls `builtin` (named `ident`) on `args` (last first, as builtins take them),
+    // going through the memo table if there is one
+    pub(super) fn call_builtin(
+        &self,
+        ident: Identifier,
+        builtin: &BuiltIn,
+        args: &[Thunk],
+    ) -> Result<Term, EvalError> {
+        let memoize = builtin.memoize && !args.is_empty();
+        let Some(memo) = self.memo.as_ref().filter(|_| memoize) else {
+            return (builtin.func)(self, args);
+        };
+
+        let shared: Vec<SharedTerm> = args.iter().map(|arg| arg.borrow().share()).collect();
+        if !shared.iter().all(|arg| self.is_closed(arg, &mut vec![])) {
+            return (builtin.func)(self, args);
+        }
+
+        let key = (ident, shared.into_iter().map(Alpha).collect::<Vec<_>>());
+        let hit = memo.table.lock().unwrap().get(&key).cloned();
+        if let Some((val, steps)) = hit {
+            memo.hits.fetch_add(1, Ordering::Relaxed);
+            Limits::charge(steps)?;
+            return Ok(Term::Val(val));
+        }
+
+        memo.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Counts the call's steps (which are still charged to any enclosing session)
+        let session = LimitSession::start(usize::MAX, usize::MAX);
+        let out = (builtin.func)(self, args);
+        let steps = session.steps();
+        drop(session);
+
+        let out = out?;
+        if let Some(val) = out.leaf_val() {
+            memo.table.lock().unwrap().insert(key, (val, steps));
+        }
+
+        Ok(out)
+    }
+
+    // Whether every free variable of `term` is a builtin
+    fn is_closed(&self, term: &SharedTerm, bound: &mut Vec<Identifier>) -> bool {
+        use SharedTerm::*;
+        match term {
+            Val(_) => true,
+            Var(v) => bound.contains(v) || self.get(*v).is_some(),
+            Lam(v, b) => {
+                bound.push(*v);
+                let closed = self.is_closed(b, bound);
+                bound.pop();
+                closed
+            }
+            App(l, r) => self.is_closed(l, bound) && self.is_closed(r, bound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `slow` takes a few reduction steps of its own, unlike `succ`
+    fn context() -> Context {
+        let slow = builtin!(
+            N => N
+            ctxt |n| => {
+                let n = n.clone();
+                ctxt.evaluate(&term!((a b c d -> a) [n] 0 0 0))?
+            }
+        )
+        .memoized();
+
+        let succ = builtin!(
+            N => N
+            |n| => Term::val(n.get::<i32>()? + 1)
+        );
+
+        context! { slow, succ }.with_memo()
+    }
+
+    fn steps(ctxt: &Context, term: &Term) -> usize {
+        let session = LimitSession::start(usize::MAX, usize::MAX);
+        ctxt.evaluate(term).unwrap();
+        session.steps()
+    }
+
+    #[test]
+    fn hits_are_charged() {
+        let ctxt = context();
+        let term = term!(slow 1);
+
+        let miss = steps(&ctxt, &term);
+        let hit = steps(&ctxt, &term);
+        assert_eq!(hit, miss);
+
+        let stats = ctxt.memo_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        // A hit runs out of fuel wherever the call itself would
+        let short = ctxt.evaluate_with_limits(&term, miss - 1, usize::MAX);
+        assert_eq!(short.unwrap_err(), EvalError::OutOfFuel);
+        assert_eq!(ctxt.memo_stats().unwrap().hits, 2);
+
+        let out = ctxt.evaluate_with_limits(&term, miss, usize::MAX).unwrap();
+        assert_eq!(out.get::<i32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn only_marked_builtins_are_memoized() {
+        let ctxt = context();
+
+        for _ in 0..2 {
+            ctxt.evaluate(&term!(succ (succ 1))).unwrap();
+        }
+
+        let stats = ctxt.memo_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (0, 0, 0));
+    }
+}
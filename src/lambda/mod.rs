@@ -1,13 +1,29 @@
+pub mod alpha;
+pub mod compile;
 pub mod context;
+pub mod data;
 pub mod env;
+pub mod error;
+pub mod memo;
+pub mod normal;
 pub mod parser;
+pub mod shared;
+pub mod symbol;
+pub mod syntax;
 pub mod vars;
 pub mod term;
 
 pub use super::*;
+pub use alpha::*;
+pub use compile::*;
 pub use context::*;
+pub use data::*;
 pub use env::*;
+pub use error::*;
+pub use memo::*;
+pub use shared::*;
+pub use symbol::*;
+pub use syntax::*;
 pub use vars::*;
 pub use term::*;
 
-use rustc_hash::FxHashSet as HashSet;
@@ -0,0 +1,117 @@
+// Full normalization. Unlike `Context::evaluate`, which stops at weak head
+// normal form, this also reduces under λ-abstractions & inside the arguments
+// of stuck applications. While reducing under a binder it is renamed to a fresh
+// uuid (so substituting open terms cannot capture variables), & the binders
+// are given readable names again once the term is normal.
+
+use super::*;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+
+impl Context {
+    // Reduces `term` to β-normal form, using this context's strategy. Builtins
+    // which cannot run (e.g. when applied to variables bound outside them) are
+    // left in place, so this only fails if evaluation runs out of resources.
+    pub fn normalize(&self, term: &Term) -> Result<Term, EvalError> {
+        let mut lenient = self.clone();
+        lenient.lenient = true;
+
+        let mut names = HashMap::default();
+        let normal = lenient.normalize_open(term, &mut names)?;
+        Ok(rename(&normal, &names, &mut vec![]))
+    }
+
+    fn normalize_open(
+        &self,
+        term: &Term,
+        names: &mut HashMap<Identifier, Identifier>,
+    ) -> Result<Term, EvalError> {
+        use Term::*;
+
+        let whnf = self.evaluate(term)?;
+        match whnf {
+            Ref(r) => self.normalize_open(&r.borrow(), names),
+            Val(_) | Var(_) => Ok(whnf),
+            Lam(v, b) => {
+                let fresh = uuid();
+                names.insert(fresh, v);
+                let body = b.instantiate_var(v, &Var(fresh).into());
+                Ok(Lam(fresh, self.normalize_open(&body, names)?.into()))
+            }
+            App(l, r) => {
+                let l = self.normalize_open(&l.borrow(), names)?;
+                let r = self.normalize_open(&r.borrow(), names)?;
+                Ok(App(l.into(), r.into()))
+            }
+        }
+    }
+}
+
+// Gives each binder its original name, or failing that the original name with
+// primes added, choosing one which no variable in its body refers to.
+fn rename(
+    term: &Term,
+    names: &HashMap<Identifier, Identifier>,
+    env: &mut Vec<(Identifier, Identifier)>,
+) -> Term {
+    use Term::*;
+    match term {
+        Ref(r) => rename(&r.borrow(), names, env),
+        Val(v) => Val(v.clone()),
+        Var(v) => match env.iter().rev().find(|(u, _)| u == v) {
+            Some((_, name)) => Var(*name),
+            None => Var(*v),
+        },
+        Lam(v, b) => {
+            let mut free = HashSet::default();
+            free_vars(b, &mut vec![*v], &mut free);
+
+            let taken: HashSet<Identifier> = free
+                .into_iter()
+                .map(|u| match env.iter().rev().find(|(w, _)| *w == u) {
+                    Some((_, name)) => *name,
+                    None => u,
+                })
+                .collect();
+
+            let base = names.get(v).copied().unwrap_or(*v);
+            let mut name = base;
+            let mut primes = 0;
+            while taken.contains(&name) {
+                primes += 1;
+                name = Identifier::intern(&format!("{}{}", base.as_str(), "'".repeat(primes)));
+            }
+
+            env.push((*v, name));
+            let body = rename(b, names, env);
+            env.pop();
+
+            Lam(name, body.into())
+        }
+        App(l, r) => App(
+            rename(&l.borrow(), names, env).into(),
+            rename(&r.borrow(), names, env).into(),
+        ),
+    }
+}
+
+fn free_vars(term: &Term, bound: &mut Vec<Identifier>, out: &mut HashSet<Identifier>) {
+    use Term::*;
+    match term {
+        Ref(r) => free_vars(&r.borrow(), bound, out),
+        Val(_) => {}
+        Var(v) => {
+            if !bound.contains(v) {
+                out.insert(*v);
+            }
+        }
+        Lam(v, b) => {
+            bound.push(*v);
+            free_vars(b, bound, out);
+            bound.pop();
+        }
+        App(l, r) => {
+            free_vars(&l.borrow(), bound, out);
+            free_vars(&r.borrow(), bound, out);
+        }
+    }
+}
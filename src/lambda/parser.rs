@@ -1,10 +1,10 @@
 #[macro_export]
 macro_rules! term {
 	(_) => {
-		$crate::Term::Var($crate::Identifier::Name("_"))
+		$crate::Term::Var($crate::Identifier::from("_"))
 	};
 	($x: ident) => {
-		$crate::Term::Var($crate::Identifier::Name(stringify!($x)))
+		$crate::Term::Var($crate::Identifier::from(stringify!($x)))
 	};
 	([:$x: expr]) => {
 		Term::val($x)
@@ -13,13 +13,13 @@ macro_rules! term {
 		$x.clone()
 	};
 	($x: literal) => {
-		$crate::Term::Val(std::rc::Rc::new($x))
+		$crate::Term::Val(std::sync::Arc::new($x))
 	};
 	($x:ident -> $($r:tt)+) => {
-		$crate::Term::Lam($crate::Identifier::Name(stringify!($x)), $crate::term!($($r)+).into())
+		$crate::Term::Lam($crate::Identifier::from(stringify!($x)), $crate::term!($($r)+).into())
 	};
 	($x:ident $($xs:ident)+ -> $($r:tt)+) => {
-		$crate::Term::Lam($crate::Identifier::Name(stringify!($x)), $crate::term!($($xs)* -> $($r)+).into())
+		$crate::Term::Lam($crate::Identifier::from(stringify!($x)), $crate::term!($($xs)* -> $($r)+).into())
 	};
 	(($($r:tt)+)) => {
 		$crate::term!($($r)+)
@@ -40,13 +40,18 @@ macro_rules! term {
 
 #[macro_export]
 macro_rules! builtin {
+	(forall $($param:ident)+ . $($rest:tt)+) => {{
+		let mut builtin = $crate::builtin!($($rest)+);
+		builtin.params = std::sync::Arc::from([$($crate::Identifier::from(stringify!($param))),+]);
+		builtin
+	}};
 	(
 		$($ty:tt)=>+
 		$(with [$($captured:ident),+] in)?
     $($ctxt:ident)? |$($arg:ident),*| => $body:expr
 	) => {{
 		use $crate::*;
-		use std::rc::Rc;
+		use std::sync::Arc;
 
 		let ty = ty!($($ty)=>+);
 
@@ -56,7 +61,7 @@ macro_rules! builtin {
 			let $captured = $captured.clone();
 		)+)?
 
-		let func = Rc::new(move |_ctxt: &Context, _args: &[Thunk]| {
+		let func = Arc::new(move |_ctxt: &Context, _args: &[Thunk]| {
 			let rev_list!([$($arg),*]) = &_args[..] else {
 				unreachable!()
 			};
@@ -69,13 +74,15 @@ macro_rules! builtin {
 				let $arg = (**$arg).borrow();
 			)*
 
-			Some($body)
+			Ok($body)
 		});
 
 		BuiltIn {
 			n_args,
 			func,
-			ty: std::rc::Rc::new(ty)
+			ty: std::sync::Arc::new(ty),
+			params: std::sync::Arc::from([]),
+			memoize: false,
 		}
 	}}
 }
@@ -85,7 +92,7 @@ macro_rules! context {
 	{$($def:ident),*} => {{
 		Context::new(
 			[$(
-				($crate::Identifier::Name(stringify!($def)), $def.clone())
+				($crate::Identifier::from(stringify!($def)), $def.clone())
 			),*].into_iter(),
 		)
 	}};
@@ -99,6 +106,15 @@ macro_rules! ty {
 	($x: ident) => {
 		$crate::Type::Var(Identifier::from(stringify!($x)))
 	};
+	(List $a:tt) => {
+		$crate::Type::List($crate::ty!($a).into())
+	};
+	(($($a:tt)=>+ , $($b:tt)=>+)) => {
+		$crate::Type::Prod($crate::ty!($($a)=>+).into(), $crate::ty!($($b)=>+).into())
+	};
+	(($($a:tt)=>+ | $($b:tt)=>+)) => {
+		$crate::Type::Sum($crate::ty!($($a)=>+).into(), $crate::ty!($($b)=>+).into())
+	};
 	($a:tt => $($b:tt)+) => {
 		$crate::Type::Fun($crate::ty!($a).into(), $crate::ty!($($b)+).into())
 	};
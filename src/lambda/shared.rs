@@ -0,0 +1,107 @@
+// Immutable, thread-safe counterpart to `Term`.
+// `Term` is built from `Rc<RefCell<_>>` thunks (so that graph reduction can
+// update shared subterms in place), which means it cannot leave the thread
+// it was built on. A `SharedTerm` can, and is cheap to clone, so it is the
+// representation used to pass programs between threads (e.g. results of
+// parallel searches, or the state of a Metropolis chain).
+
+use super::*;
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub enum SharedTerm {
+    Val(Value),
+    Var(Identifier),
+    Lam(Identifier, Arc<SharedTerm>),
+    App(Arc<SharedTerm>, Arc<SharedTerm>),
+}
+
+impl SharedTerm {
+    pub fn to_term(&self) -> Term {
+        Term::from(self)
+    }
+
+    pub fn size(&self) -> usize {
+        use SharedTerm::*;
+        match self {
+            Val(_) | Var(_) => 1,
+            Lam(_, b) => 1 + b.size(),
+            App(l, r) => 1 + l.size() + r.size(),
+        }
+    }
+}
+
+impl Term {
+    pub fn share(&self) -> SharedTerm {
+        SharedTerm::from(self)
+    }
+}
+
+impl From<&Term> for SharedTerm {
+    fn from(term: &Term) -> Self {
+        use Term::*;
+        match term {
+            Ref(r) => Self::from(&*r.borrow()),
+            Val(v) => Self::Val(v.clone()),
+            Var(v) => Self::Var(*v),
+            Lam(v, b) => Self::Lam(*v, Arc::new(Self::from(&**b))),
+            App(l, r) => Self::App(
+                Arc::new(Self::from(&*l.borrow())),
+                Arc::new(Self::from(&*r.borrow())),
+            ),
+        }
+    }
+}
+
+impl From<&SharedTerm> for Term {
+    fn from(term: &SharedTerm) -> Self {
+        use SharedTerm::*;
+        match term {
+            Val(v) => Self::Val(v.clone()),
+            Var(v) => Self::Var(*v),
+            Lam(v, b) => Self::Lam(*v, Term::from(&**b).into()),
+            App(l, r) => Self::App(Term::from(&**l).into(), Term::from(&**r).into()),
+        }
+    }
+}
+
+impl From<Term> for SharedTerm {
+    fn from(term: Term) -> Self {
+        Self::from(&term)
+    }
+}
+
+impl From<SharedTerm> for Term {
+    fn from(term: SharedTerm) -> Self {
+        Self::from(&term)
+    }
+}
+
+// Syntactic equality, as for `Term` (see `SharedTerm::alpha_eq`)
+impl PartialEq for SharedTerm {
+    fn eq(&self, other: &Self) -> bool {
+        use SharedTerm::*;
+        match (self, other) {
+            (Val(a), Val(b)) => a.is_eq(b),
+            (Var(a), Var(b)) => a == b,
+            (Lam(va, ba), Lam(vb, bb)) => va == vb && ba == bb,
+            (App(ll, lr), App(rl, rr)) => ll == rl && lr == rr,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for SharedTerm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_term())
+    }
+}
+
+// Everything needed to set up evaluation or search on another thread
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedTerm>();
+    assert_send_sync::<Context>();
+    assert_send_sync::<Type>();
+    assert_send_sync::<Identifier>();
+};
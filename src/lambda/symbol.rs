@@ -0,0 +1,93 @@
+// Interned names. Each distinct name is stored exactly once in a global
+// table, so a `Symbol` is just a pointer to its canonical copy: equality &
+// hashing are O(1) pointer operations, and creating the same name at runtime
+// repeatedly (e.g. from a parser) does not allocate after the first time.
+
+use rustc_hash::FxHashSet as HashSet;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::sync::{OnceLock, RwLock};
+
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+fn table() -> &'static RwLock<HashSet<&'static str>> {
+    static TABLE: OnceLock<RwLock<HashSet<&'static str>>> = OnceLock::new();
+    TABLE.get_or_init(Default::default)
+}
+
+impl Symbol {
+    // Interns a string which is already static (no allocation required)
+    pub fn new_static(name: &'static str) -> Self {
+        Self::intern_with(name, || name)
+    }
+
+    pub fn new(name: &str) -> Self {
+        Self::intern_with(name, || Box::leak(name.into()))
+    }
+
+    fn intern_with(name: &str, store: impl FnOnce() -> &'static str) -> Self {
+        if let Some(&canon) = table().read().unwrap().get(name) {
+            return Self(canon);
+        }
+
+        let mut table = table().write().unwrap();
+
+        // Someone else may have interned `name` since we released the lock
+        if let Some(&canon) = table.get(name) {
+            return Self(canon);
+        }
+
+        let canon = store();
+        table.insert(canon);
+        Self(canon)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.0, state)
+    }
+}
+
+// Ordered by name, so that sorted identifiers do not depend on interning order
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.0.cmp(other.0)
+        }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
@@ -0,0 +1,409 @@
+// Runtime parser for terms & types. Accepts the syntax produced by the
+// `Display` impls for `Term` and `Type`, so that printed programs (e.g. the
+// `Solution found for ...` lines in `data/`) can be read back in.
+//
+// Terms:
+//   term  := '\' ident+ '->' term | atom ('(' term ')')*
+//   atom  := '(' term ')' | '(' op ')' | literal | ident
+// Types:
+//   type  := tatom ('=>' type)?
+//   tatom := '(' type (',' type | '|' type)? ')' | 'List' tatom | ident
+//
+// Integer literals are read as `i32` & `true`/`false` as `bool`. Operator
+// names wrapped in parentheses (e.g. `(+)`) are read as single identifiers,
+// and `_n` is read as `Identifier::Uuid(n)`.
+
+use super::*;
+
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub span: Range<usize>,
+    pub msg: String,
+}
+
+impl ParseError {
+    fn new(span: Range<usize>, msg: impl Into<String>) -> Self {
+        Self {
+            span,
+            msg: msg.into(),
+        }
+    }
+
+    // Renders the offending line of `src` with the span underlined
+    pub fn annotate(&self, src: &str) -> String {
+        let start = self.span.start.min(src.len());
+        let line_start = src[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+
+        let line = &src[line_start..line_end];
+        let offset = src[line_start..start].chars().count();
+        let end = self.span.end.clamp(start, line_end);
+        let width = src[start..end].chars().count().max(1);
+
+        format!(
+            "{}\n{:offset$}{} {}",
+            line,
+            "",
+            "^".repeat(width),
+            self.msg,
+            offset = offset
+        )
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at {}..{})", self.msg, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type ParseResult<T> = std::result::Result<T, ParseError>;
+
+impl Term {
+    pub fn parse(src: &str) -> ParseResult<Term> {
+        let mut parser = Parser::new(src);
+        let term = parser.term()?;
+        parser.finish()?;
+        Ok(term)
+    }
+}
+
+impl Type {
+    pub fn parse(src: &str) -> ParseResult<Type> {
+        let mut parser = Parser::new(src);
+        let ty = parser.ty()?;
+        parser.finish()?;
+        Ok(ty)
+    }
+}
+
+impl std::str::FromStr for Term {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        Term::parse(s)
+    }
+}
+
+impl std::str::FromStr for Type {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> ParseResult<Self> {
+        Type::parse(s)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> ParseResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", token)))
+        }
+    }
+
+    fn unexpected(&mut self, expected: &str) -> ParseError {
+        self.skip_ws();
+        match self.rest().chars().next() {
+            Some(c) => ParseError::new(
+                self.pos..self.pos + c.len_utf8(),
+                format!("expected {}, found `{}`", expected, c),
+            ),
+            None => ParseError::new(
+                self.pos..self.pos,
+                format!("expected {}, found end of input", expected),
+            ),
+        }
+    }
+
+    fn finish(&mut self) -> ParseResult<()> {
+        self.skip_ws();
+        if self.rest().is_empty() {
+            Ok(())
+        } else {
+            Err(ParseError::new(
+                self.pos..self.src.len(),
+                "unexpected trailing input",
+            ))
+        }
+    }
+
+    // Scans a maximal run of identifier characters, returning its span
+    fn word(&mut self) -> Range<usize> {
+        self.skip_ws();
+        let start = self.pos;
+        let rest = self.rest();
+
+        let mut len = 0;
+        for (i, c) in rest.char_indices() {
+            let arrow = rest[i..].starts_with("->") || rest[i..].starts_with("=>");
+            if c.is_whitespace() || "()\\,|".contains(c) || arrow {
+                break;
+            }
+            len = i + c.len_utf8();
+        }
+
+        self.pos += len;
+        start..self.pos
+    }
+
+    fn ident(&mut self) -> ParseResult<(Identifier, Range<usize>)> {
+        let span = self.word();
+        if span.is_empty() {
+            return Err(self.unexpected("identifier"));
+        }
+
+        let word = &self.src[span.clone()];
+        Ok((make_ident(word), span))
+    }
+
+    fn param(&mut self) -> ParseResult<Identifier> {
+        let (param, span) = self.ident()?;
+        if literal(&self.src[span.clone()]).is_some() {
+            return Err(ParseError::new(span, "expected parameter name"));
+        }
+        Ok(param)
+    }
+
+    // An operator wrapped in parentheses, such as `(+)`
+    fn op_ident(&mut self) -> Option<Identifier> {
+        self.skip_ws();
+        let rest = self.rest().strip_prefix('(')?;
+        let len = rest.find(')')?;
+        let op = &rest[..len];
+
+        let is_op_char = |c: char| c.is_ascii_punctuation() && !"()\\_'".contains(c);
+        if op.is_empty() || !op.chars().all(is_op_char) || op == "->" {
+            return None;
+        }
+
+        let name = &self.rest()[..len + 2];
+        self.pos += len + 2;
+        Some(make_ident(name))
+    }
+
+    fn term(&mut self) -> ParseResult<Term> {
+        if self.eat("\\") {
+            let mut params = vec![self.param()?];
+            while !self.eat("->") {
+                params.push(self.param()?);
+            }
+
+            let mut body = self.term()?;
+            for param in params.into_iter().rev() {
+                body = Term::Lam(param, body.into());
+            }
+            return Ok(body);
+        }
+
+        let mut term = self.atom()?;
+        while self.peek() == Some('(') {
+            self.expect("(")?;
+            let arg = self.term()?;
+            self.expect(")")?;
+            term = Term::App(term.into(), arg.into());
+        }
+
+        Ok(term)
+    }
+
+    fn atom(&mut self) -> ParseResult<Term> {
+        if let Some(op) = self.op_ident() {
+            return Ok(Term::Var(op));
+        }
+
+        if self.eat("(") {
+            let term = self.term()?;
+            self.expect(")")?;
+            return Ok(term);
+        }
+
+        if self.peek().is_none_or(|c| "()\\,|".contains(c)) {
+            return Err(self.unexpected("term"));
+        }
+
+        let span = self.word();
+        let word = &self.src[span];
+        Ok(literal(word).unwrap_or_else(|| Term::Var(make_ident(word))))
+    }
+
+    fn ty(&mut self) -> ParseResult<Type> {
+        let arg = self.ty_atom()?;
+        if self.eat("=>") {
+            let ret = self.ty()?;
+            Ok(Type::Fun(arg.into(), ret.into()))
+        } else {
+            Ok(arg)
+        }
+    }
+
+    fn ty_atom(&mut self) -> ParseResult<Type> {
+        if self.eat("(") {
+            let mut ty = self.ty()?;
+            if self.eat(",") {
+                ty = Type::Prod(ty.into(), self.ty()?.into());
+            } else if self.eat("|") {
+                ty = Type::Sum(ty.into(), self.ty()?.into());
+            }
+            self.expect(")")?;
+            return Ok(ty);
+        }
+
+        if self.peek().is_none_or(|c| "()\\,|".contains(c)) {
+            return Err(self.unexpected("type"));
+        }
+
+        let (ident, _) = self.ident()?;
+        if ident.name() == Some("List") {
+            return Ok(Type::List(self.ty_atom()?.into()));
+        }
+
+        Ok(Type::Var(ident))
+    }
+}
+
+fn literal(word: &str) -> Option<Term> {
+    match word {
+        "true" => Some(Term::val(true)),
+        "false" => Some(Term::val(false)),
+        _ => word.parse::<i32>().ok().map(Term::val),
+    }
+}
+
+fn make_ident(word: &str) -> Identifier {
+    if let Some(Ok(n)) = word.strip_prefix('_').map(str::parse::<u128>) {
+        return Identifier::Uuid(n);
+    }
+
+    Identifier::intern(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::*;
+
+    fn round_trip(term: &Term) {
+        let printed = term.to_string();
+        assert_eq!(Term::parse(&printed).as_ref(), Ok(term), "{}", printed);
+    }
+
+    #[test]
+    fn terms_round_trip() {
+        let terms = [
+            term!(a b -> plus a b),
+            term!(f -> f (x -> x) y),
+            term!(x -> (y -> y) x),
+            term!(f x -> f (f x) [:3]),
+            term!([:true] [:(-4)] [:false]),
+            Term::App(
+                Term::Var(Identifier::from("(+)")).into(),
+                Term::Var(Identifier::Uuid(17)).into(),
+            ),
+        ];
+
+        for term in &terms {
+            round_trip(term);
+        }
+    }
+
+    #[test]
+    fn enumerated_terms_round_trip() {
+        for size in 1..=7 {
+            for (term, _) in search(&Polynomials, vec![], &ty!(N => N => N), size) {
+                round_trip(&term);
+            }
+            for (term, _) in search(&CondPolyLang, vec![], &ty!(Poly => N), size) {
+                round_trip(&term);
+            }
+        }
+    }
+
+    #[test]
+    fn types_round_trip() {
+        let types = [
+            ty!(N),
+            ty!(N => N => N),
+            ty!((N => N) => N),
+            ty!((N, N => Bool)),
+            ty!((N | (List N))),
+            ty!(List (N, N)),
+            ty!((List N) => (N | Bool)),
+        ];
+
+        for ty in &types {
+            let printed = ty.to_string();
+            assert_eq!(Type::parse(&printed).as_ref(), Ok(ty), "{}", printed);
+        }
+    }
+
+    #[test]
+    fn data_types_parse() {
+        let parse = |src: &str| Type::parse(src).unwrap();
+
+        assert_eq!(parse("(N, Bool)"), ty!((N, Bool)));
+        assert_eq!(parse("(N | Bool)"), ty!((N | Bool)));
+        assert_eq!(parse("List N"), ty!(List N));
+        assert_eq!(parse("List (List N)"), ty!(List (List N)));
+        assert_eq!(parse("(N => N, Bool)"), ty!((N => N, Bool)));
+        assert_eq!(parse("(N, Bool) => N"), ty!((N, Bool) => N));
+        assert_eq!(parse("List N => N"), ty!((List N) => N));
+
+        assert!(Type::parse("(N, Bool | N)").is_err());
+        assert!(Type::parse("(N,)").is_err());
+        assert!(Type::parse("List").is_err());
+    }
+
+    #[test]
+    fn error_spans() {
+        let span = |result: ParseResult<Term>| result.unwrap_err().span;
+
+        assert_eq!(span(Term::parse("(\\a -> a")), 8..8);
+        assert_eq!(span(Term::parse("f(x) )")), 5..6);
+        assert_eq!(span(Term::parse("\\1 -> a")), 1..2);
+        assert_eq!(span(Term::parse("f(,)")), 2..3);
+        assert_eq!(Type::parse("N => ").unwrap_err().span, 5..5);
+
+        let src = "f(x) )";
+        let err = Term::parse(src).unwrap_err();
+        assert_eq!(err.annotate(src), "f(x) )\n     ^ unexpected trailing input");
+    }
+}
@@ -2,10 +2,11 @@ use super::*;
 use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 
 pub type Thunk = Rc<RefCell<Term>>;
 
-pub type Value = Rc<dyn TermValue>;
+pub type Value = Arc<dyn TermValue>;
 
 #[derive(Clone, Debug)]
 pub enum Term {
@@ -23,7 +24,7 @@ pub enum Term {
 
 impl Term {
     pub fn val<T: TermValue>(x: T) -> Self {
-        Self::Val(Rc::new(x))
+        Self::Val(Arc::new(x))
     }
 
     pub fn deep_clone(&self) -> Self {
@@ -41,6 +42,11 @@ impl Term {
     }
 
     pub fn instantiate_var(&self, var: Identifier, thunk: &Thunk) -> Term {
+        self.substitute(var, &mut || Term::Ref(thunk.clone()))
+    }
+
+    // Replaces free occurrences of `var`, building a separate replacement for each
+    pub fn substitute(&self, var: Identifier, arg: &mut impl FnMut() -> Term) -> Term {
         use Term::*;
         match self {
             Val(n) => Val(n.clone()),
@@ -48,23 +54,23 @@ impl Term {
                 if *v == var {
                     Lam(*v, b.clone())
                 } else {
-                    Lam(*v, b.instantiate_var(var, thunk).into())
+                    Lam(*v, b.substitute(var, arg).into())
                 }
             }
             Var(v) => {
                 if *v == var {
-                    Ref(thunk.clone())
+                    arg()
                 } else {
                     Var(*v)
                 }
             }
             Ref(next) => {
                 let next = &*(**next).borrow();
-                next.instantiate_var(var, thunk)
+                next.substitute(var, arg)
             }
             App(l, r) => App(
-                (**l).borrow().instantiate_var(var, thunk).into(),
-                (**r).borrow().instantiate_var(var, thunk).into(),
+                (**l).borrow().substitute(var, arg).into(),
+                (**r).borrow().substitute(var, arg).into(),
             ),
         }
     }
@@ -88,10 +94,22 @@ impl Term {
         }
     }
 
-    pub fn get<T: TermValue + Clone>(&self) -> T {
-        let rc = self.leaf_val().unwrap();
+    // Like `leaf_val`, but explains why there is no value
+    pub fn value(&self) -> std::result::Result<Value, EvalError> {
+        self.leaf_val()
+            .ok_or_else(|| EvalError::Stuck(self.to_string()))
+    }
+
+    pub fn get<T: TermValue + Clone>(&self) -> std::result::Result<T, EvalError> {
+        let rc = self.value()?;
         let any = rc.as_any();
-        any.downcast_ref::<T>().unwrap().clone()
+        match any.downcast_ref::<T>() {
+            Some(out) => Ok(out.clone()),
+            None => Err(EvalError::TypeMismatch {
+                expected: std::any::type_name::<T>(),
+                found: rc.to_string(),
+            }),
+        }
     }
 
     pub fn try_get<T: TermValue + Clone>(&self) -> Option<T> {
@@ -129,7 +147,7 @@ pub fn cast<T: TermValue>(rc: &Value) -> Option<&T> {
     rc.as_any().downcast_ref()
 }
 
-pub trait TermValue: Any + Debug + Display {
+pub trait TermValue: Any + Debug + Display + Send + Sync {
     // For some reason requiring PartialEq is bad but this is okay?
     // If you don't love Rust at its trait bound restrictions,
     // you don't deserve it at its... uhhh... nevermind...
@@ -137,7 +155,7 @@ pub trait TermValue: Any + Debug + Display {
     fn as_any(&self) -> &dyn Any;
 }
 
-impl<T: Any + Debug + Display + PartialEq> TermValue for T {
+impl<T: Any + Debug + Display + PartialEq + Send + Sync> TermValue for T {
     fn is_eq(&self, other: &Value) -> bool {
         let Some(other) = other.as_any().downcast_ref::<T>() else {
             return false;
@@ -150,7 +168,7 @@ impl<T: Any + Debug + Display + PartialEq> TermValue for T {
     }
 }
 
-//Syntactic equality, not α-equality (might be useful to implement eventually)
+//Syntactic equality, not α-equality (see `Term::alpha_eq`)
 impl PartialEq for Term {
     fn eq(&self, other: &Self) -> bool {
         use Term::*;
@@ -1,18 +1,34 @@
+use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::fmt::{Debug, Display};
 
 use super::*;
 
 #[derive(Hash, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
 pub enum Identifier {
-    Name(&'static str),
+    Name(Symbol),
     Uuid(u128),
 }
 
 impl Identifier {
-    pub fn as_str(&self) -> &str {
+    pub fn intern(name: &str) -> Self {
+        Self::Name(Symbol::new(name))
+    }
+
+    // `None` for uuids
+    pub fn name(&self) -> Option<&'static str> {
         match self {
-            Self::Name(n) => n,
-            _ => unreachable!(),
+            Self::Name(n) => Some(n.as_str()),
+            Self::Uuid(_) => None,
+        }
+    }
+
+    // As displayed. Uuids (`_n`) are formatted on demand rather than interned,
+    // since a long run creates an unbounded number of them
+    pub fn as_str(&self) -> Cow<'static, str> {
+        match self {
+            Self::Name(n) => Cow::Borrowed(n.as_str()),
+            Self::Uuid(u) => Cow::Owned(format!("_{}", u)),
         }
     }
 }
@@ -26,9 +42,9 @@ const IDENTS: &[&str] = &[
     "κ", "λ", "μ", "ξ", "π", "ρ", "ς", "τ", "φ", "χ", "ψ", "ω",
 ];
 
-//Variable generator
+//Variable generator (ordered, so the same names are chosen on every run)
 pub struct VarGen {
-    free: HashSet<Identifier>,
+    free: BTreeSet<Identifier>,
 }
 
 impl VarGen {
@@ -37,8 +53,13 @@ impl VarGen {
         self.free.take(&var).unwrap()
     }
 
+    // Any free variable once the lowercase ones run out
     pub fn small_var(&mut self) -> Identifier {
-        self.find_with(|c| c.is_ascii_lowercase())
+        let lower = |s: &&Identifier| s.as_str().starts_with(|c: char| c.is_ascii_lowercase());
+        match self.free.iter().find(lower).copied() {
+            Some(var) => self.free.take(&var).unwrap(),
+            None => self.newvar(),
+        }
     }
 
     pub fn cap_var(&mut self) -> Identifier {
@@ -63,8 +84,8 @@ impl VarGen {
 impl Default for VarGen {
     fn default() -> Self {
         Self {
-            free: HashSet::from_iter(
-                IDENTS.iter().copied().map(Identifier::Name),
+            free: BTreeSet::from_iter(
+                IDENTS.iter().copied().map(Identifier::from),
             ),
         }
     }
@@ -76,22 +97,16 @@ pub fn new_var_where(
     IDENTS
         .iter()
         .copied()
-        .map(Identifier::Name)
+        .map(Identifier::from)
         .find(|&id| p(id))
 }
 
 pub fn uuid() -> Identifier {
-    // Technically unsafe in a multithreaded setting, but I'll be shocked if this isn't fine forever.
+    use std::sync::atomic::{AtomicU64, Ordering};
 
-    static mut COUNTER: u128 = 0;
-
-    let x;
-    unsafe {
-        x = COUNTER;
-        COUNTER += 1;
-    }
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
 
-    Identifier::Uuid(x)
+    Identifier::Uuid(COUNTER.fetch_add(1, Ordering::Relaxed) as u128)
 }
 
 impl Display for Identifier {
@@ -112,7 +127,13 @@ impl Debug for Identifier {
 
 impl From<&'static str> for Identifier {
     fn from(s: &'static str) -> Self {
-        Self::Name(s)
+        Self::Name(Symbol::new_static(s))
+    }
+}
+
+impl From<String> for Identifier {
+    fn from(s: String) -> Self {
+        Self::intern(&s)
     }
 }
 
@@ -121,3 +142,17 @@ impl From<u128> for Identifier {
         Self::Uuid(u)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuids_are_not_interned() {
+        let name = Identifier::intern("plus").as_str();
+        assert!(matches!(name, Cow::Borrowed("plus")));
+
+        let uuid = Identifier::Uuid(12).as_str();
+        assert!(matches!(&uuid, Cow::Owned(s) if s == "_12"));
+    }
+}
@@ -1,5 +1,5 @@
 use super::*;
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Copy, Debug)]
 pub struct CondPolyLang;
@@ -35,17 +35,17 @@ impl Language for CondPolyLang {
     fn context(&self) -> Context {
         let plus = builtin!(
             Poly => Poly => Poly
-            |x, y| => Term::val(x.get::<i32>().wrapping_add(y.get::<i32>()))
+            |x, y| => Term::val(x.get::<i32>()?.wrapping_add(y.get::<i32>()?))
         );
 
         let sub = builtin!(
             Poly => Poly => Poly
-            |x, y| => Term::val(x.get::<i32>().wrapping_sub(y.get::<i32>()))
+            |x, y| => Term::val(x.get::<i32>()?.wrapping_sub(y.get::<i32>()?))
         );
 
         let mult = builtin!(
             Poly => Poly => Poly
-            |x, y| => Term::val(x.get::<i32>().wrapping_mul(y.get::<i32>()))
+            |x, y| => Term::val(x.get::<i32>()?.wrapping_mul(y.get::<i32>()?))
         );
 
         let one = builtin!(
@@ -60,7 +60,7 @@ impl Language for CondPolyLang {
 
         let case = builtin!(
             Cond => Poly => Cases => Cases
-            |c| => if c.get::<bool>() {
+            |c| => if c.get::<bool>()? {
                 term!(p q -> p)
             } else {
                 term!(p q -> q)
@@ -69,27 +69,27 @@ impl Language for CondPolyLang {
 
         let orelse = builtin!(
             Poly => Cases
-            |p| => Term::val(p.get::<i32>())
+            |p| => Term::val(p.get::<i32>()?)
         );
 
         let eval = builtin!(
             Cases => N
-            |c| => Term::val(c.get::<i32>())
+            |c| => Term::val(c.get::<i32>()?)
         );
 
         let eqz = builtin!(
             Poly => Cond
-            |p| => Term::val(p.get::<i32>() == 0)
+            |p| => Term::val(p.get::<i32>()? == 0)
         );
 
         let pos = builtin!(
             Poly => Cond
-            |p| => Term::val(p.get::<i32>() > 0)
+            |p| => Term::val(p.get::<i32>()? > 0)
         );
 
         let and = builtin!(
             Cond => Cond => Cond
-            |a, b| => Term::val(a.get::<bool>() && b.get::<bool>())
+            |a, b| => Term::val(a.get::<bool>()? && b.get::<bool>()?)
         );
 
         let def = builtin!(
@@ -126,7 +126,7 @@ impl Language for CondPolyLang {
             "pos", "and", "def",
         ];
 
-        match v.as_str() {
+        match &*v.as_str() {
             "'1'" => Canonical(Poly(Sum::from(1))),
             "'0'" => Canonical(Poly(Sum::from(0))),
             s if names.contains(&s) => Canonical(Appl(v, vec![])),
@@ -166,13 +166,13 @@ impl Language for CondPolyLang {
         match fun {
             Appl(v, mut args)
                 if args.len() == 1
-                    && ["(+)", "(-)", "(*)"].contains(&v.as_str()) =>
+                    && ["(+)", "(-)", "(*)"].contains(&&*v.as_str()) =>
             {
                 let (Poly(a), Poly(b)) = (args.remove(0), arg) else {
                     unreachable!()
                 };
 
-                let mut poly = match v.as_str() {
+                let mut poly = match &*v.as_str() {
                     "(+)" => a.add(&b),
                     "(-)" => a.add(&b.mul(&Sum::from(-1))),
                     "(*)" => a.mul(&b),
@@ -183,7 +183,7 @@ impl Language for CondPolyLang {
                 Canonical(Poly(poly))
             }
             Appl(v, mut args)
-                if ["eqz", "pos"].contains(&v.as_str()) && args.len() == 1 =>
+                if ["eqz", "pos"].contains(&&*v.as_str()) && args.len() == 1 =>
             {
                 let Poly(p) = args.remove(0) else {
                     unreachable!()
@@ -192,7 +192,7 @@ impl Language for CondPolyLang {
                 let mut eqzs = vec![];
                 let mut poss = vec![];
 
-                let kind = match v.as_str() {
+                let kind = match &*v.as_str() {
                     "eqz" => &mut eqzs,
                     "pos" => &mut poss,
                     _ => unreachable!(),
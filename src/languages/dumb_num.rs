@@ -1,4 +1,4 @@
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Copy, Debug)]
 pub struct DumbNum;
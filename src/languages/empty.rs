@@ -1,6 +1,6 @@
 use super::*;
 
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Empty;
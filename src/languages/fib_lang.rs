@@ -1,4 +1,4 @@
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Debug)]
 pub struct FibLang;
@@ -11,7 +11,7 @@ impl Language for FibLang {
 
         let lte = builtin!(
             N => N => N => N => N
-            |a, b| => if int(&a) <= int(&b) {
+            |a, b| => if int(&a)? <= int(&b)? {
                 term!(a b -> a)
             } else {
                 term!(a b -> b)
@@ -20,12 +20,12 @@ impl Language for FibLang {
 
         let plus = builtin!(
             N => N => N
-            |x, y| => Term::val(int(&x)+int(&y))
+            |x, y| => Term::val(int(&x)?.checked_add(int(&y)?).ok_or(EvalError::Overflow)?)
         );
 
         let minus = builtin!(
             N => N => N
-            |x, y| => Term::val(int(&x)-int(&y))
+            |x, y| => Term::val(int(&x)?.checked_sub(int(&y)?).ok_or(EvalError::Overflow)?)
         );
 
         let one = builtin!(
@@ -1,9 +1,9 @@
 #![allow(dead_code)]
 
 use std::fmt::{write, Display};
-use std::rc::Rc;
+use std::sync::Arc;
 
-use kolmogorov::*;
+use crate::*;
 
 use super::polynomials::*;
 
@@ -31,7 +31,7 @@ type Conjunction = Vec<Literal>;
 pub struct Exists {
     var: Identifier,
     bound: Number,
-    body: Rc<LogicSems>,
+    body: Arc<LogicSems>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -40,7 +40,7 @@ pub enum LogicSems {
     And(Conjunction),                // A conjunction of Literals
     App(Identifier, Vec<LogicSems>), // Variables are not analyzed until they are contextualized
     Any(Exists),
-    Abs(Identifier, Rc<LogicSems>),
+    Abs(Identifier, Arc<LogicSems>),
 }
 
 impl Language for LogicLang {
@@ -56,13 +56,12 @@ impl Language for LogicLang {
 
     fn svar(&self, v: Identifier, ty: &Type) -> Analysis<Self> {
         use Analysis::*;
-        use Identifier::*;
         use LogicSems::*;
         use Type::*;
         match ty {
             // Disallow function variables
             Fun(_, _) if self.context.get(v).is_none() => Malformed,
-            Var(Name("N")) => Canonical(Val(v)),
+            Var(t) if t.name() == Some("N") => Canonical(Val(v)),
             _ => Canonical(App(v, vec![])),
         }
     }
@@ -83,7 +82,7 @@ impl Language for LogicLang {
             return Malformed;
         }
 
-        Canonical(Abs(ident, Rc::new(body.canon())))
+        Canonical(Abs(ident, Arc::new(body.canon())))
     }
 
     fn sapp(&self, fun: Analysis<Self>, arg: Analysis<Self>, _ty: &Type) -> Analysis<Self> {
@@ -162,25 +161,31 @@ impl LogicLang {
         let exists = builtin! {
             N => (N => Bool) => Bool
             ctxt |b, f| => {
-                Term::val((1..b.get::<u32>()).any(|n| ctxt.evaluate(&term!([f] [:n])).get::<bool>()))
+                let found = (1..b.get::<u32>()?)
+                    .map(|n| ctxt.evaluate(&term!([f] [:n]))?.get::<bool>())
+                    .find(|r| r != &Ok(false))
+                    .unwrap_or(Ok(false))?;
+                Term::val(found)
             }
-        };
+        }
+        .memoized();
 
         let and = builtin! {
             Bool => Bool => Bool
-            |a, b| => Term::val(a.get::<bool>() && b.get::<bool>())
+            |a, b| => Term::val(a.get::<bool>()? && b.get::<bool>()?)
         };
 
         let prime = builtin! {
             N => Bool
-            |n| => Term::val(is_prime(n.get::<u32>()))
-        };
+            |n| => Term::val(is_prime(n.get::<u32>()?))
+        }
+        .memoized();
 
         let divisor = builtin! {
             N => N => Bool
             |p, q| => {
-                let p = p.get::<u32>();
-                let q = q.get::<u32>();
+                let p = p.get::<u32>()?;
+                let q = q.get::<u32>()?;
                 Term::val(p > 1 && q % p == 0)
             }
         };
@@ -212,7 +217,7 @@ fn is_prime(n: u32) -> bool {
     }
     let mut i = 2;
     while i * i < n {
-        if n % i == 0 {
+        if n.is_multiple_of(i) {
             return false;
         }
         i += 1;
@@ -0,0 +1,22 @@
+// Example languages, shared by the binaries & tests
+
+#![allow(unused_imports)]
+
+pub mod dumb_num;
+pub mod cond_poly;
+pub mod empty;
+pub mod fib_lang;
+pub mod fol;
+pub mod num_logic;
+pub mod opaque;
+pub mod polynomials;
+
+pub use dumb_num::*;
+pub use cond_poly::*;
+pub use empty::*;
+pub use fib_lang::*;
+// Both logics define a `Predicate`, which is left to their modules
+pub use fol::{Exists, LogicLang, LogicSems};
+pub use num_logic::{Atom, NumLogic, NumLogicSems, Reducer, Reduction};
+pub use opaque::*;
+pub use polynomials::*;
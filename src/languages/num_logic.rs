@@ -1,8 +1,8 @@
 use std::fmt::{write, Display};
 use std::process::id;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use kolmogorov::*;
+use crate::*;
 
 use super::polynomials::*;
 
@@ -48,7 +48,7 @@ pub struct Reduction {
     reducer: Reducer,
     var: Identifier,
     bound: Var,
-    body: Rc<NumLogicSems>,
+    body: Arc<NumLogicSems>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -57,7 +57,7 @@ pub enum NumLogicSems {
     And(Conjunction),                   // A conjunction of Literals
     App(Identifier, Vec<NumLogicSems>), // Variables are not analyzed until they are contextualized
     Red(Reduction),
-    Abs(Identifier, Rc<NumLogicSems>),
+    Abs(Identifier, Arc<NumLogicSems>),
 }
 
 impl Language for NumLogic {
@@ -74,13 +74,14 @@ impl Language for NumLogic {
     fn svar(&self, v: Identifier, ty: &Type) -> Analysis<Self> {
         use Analysis::*;
         use Atom::*;
-        use Identifier::*;
         use NumLogicSems::*;
 
         match ty {
             // Disallow function variables
             Type::Fun(_, _) if self.context.get(v).is_none() => Malformed,
-            Type::Var(Name("Atom" | "Var")) => Canonical(Mul(vec![Var(v)])),
+            Type::Var(t) if matches!(t.name(), Some("Atom" | "Var")) => {
+                Canonical(Mul(vec![Var(v)]))
+            }
             _ => Canonical(App(v, vec![])),
         }
     }
@@ -101,7 +102,7 @@ impl Language for NumLogic {
             return Malformed;
         }
 
-        Canonical(Abs(ident, Rc::new(body.canon())))
+        Canonical(Abs(ident, Arc::new(body.canon())))
     }
 
     fn sapp(&self, fun: Analysis<Self>, arg: Analysis<Self>, _ty: &Type) -> Analysis<Self> {
@@ -179,7 +180,7 @@ impl Language for NumLogic {
                 And(bools)
             }
             App(v, mut args)
-                if ["sigma", "count", "exists"].contains(&v.as_str()) && args.len() == 1 =>
+                if ["sigma", "count", "exists"].contains(&&*v.as_str()) && args.len() == 1 =>
             {
                 let Mul(mut sum) = args.remove(0) else {
                     unreachable!()
@@ -197,7 +198,7 @@ impl Language for NumLogic {
                 }
 
                 use Reducer::*;
-                let reducer = match v.as_str() {
+                let reducer = match &*v.as_str() {
                     "exists" => Existential,
                     "sigma" => Sigma,
                     "count" => Count,
@@ -211,7 +212,7 @@ impl Language for NumLogic {
                     body,
                 })
             }
-            App(v, _) if ["num", "atom", "conj", "bool"].contains(&v.as_str()) => arg,
+            App(v, _) if ["num", "atom", "conj", "bool"].contains(&&*v.as_str()) => arg,
             App(v, mut args) => {
                 args.push(arg);
                 App(v, args)
@@ -238,6 +239,9 @@ impl NumLogic {
         let int = |t: &Term| t.get::<u32>();
         let bln = |t: &Term| t.get::<bool>();
 
+        let eval_int = |ctxt: &Context, t: Term| ctxt.evaluate(&t)?.get::<u32>();
+        let eval_bln = |ctxt: &Context, t: Term| ctxt.evaluate(&t)?.get::<bool>();
+
         let atom = builtin! {
             Var => Atom
             |v| => v.clone()
@@ -245,20 +249,25 @@ impl NumLogic {
 
         let pow = builtin! {
             Var => Var => Atom
-            |c, p| => Term::val(int(&c).checked_pow(int(&p)).unwrap_or(0))
+            |c, p| => Term::val(int(&c)?.checked_pow(int(&p)?).unwrap_or(0))
         };
 
         let mul = builtin! {
             Atom => Atom => Atom
-            |l, r| => Term::val(int(&l).checked_mul(int(&r)).unwrap_or(0))
+            |l, r| => Term::val(int(&l)?.checked_mul(int(&r)?).unwrap_or(0))
         };
 
         let exists = builtin! {
             Var => (Var => Bool) => Bool
             ctxt |b, f| => {
-                Term::val((1..=int(&b)).any(|n| bln(&ctxt.evaluate(&term!([f] [:n])))))
+                let found = (1..=int(&b)?)
+                    .map(|n| eval_bln(ctxt, term!([f] [:n])))
+                    .find(|r| r != &Ok(false))
+                    .unwrap_or(Ok(false))?;
+                Term::val(found)
             }
-        };
+        }
+        .memoized();
 
         let num = builtin! {
             Atom => Num
@@ -268,16 +277,25 @@ impl NumLogic {
         let sigma = builtin! {
             Var => (Var => Num) => Num
             ctxt |b, f| => {
-                Term::val((1..=int(&b)).map(|n| int(&ctxt.evaluate(&term!([f] [:n])))).sum::<u32>())
+                let sum = (1..=int(&b)?).try_fold(0u32, |sum, n| {
+                    let term = eval_int(ctxt, term!([f] [:n]))?;
+                    sum.checked_add(term).ok_or(EvalError::Overflow)
+                })?;
+                Term::val(sum)
             }
-        };
+        }
+        .memoized();
 
         let count = builtin! {
             Var => (Var => Bool) => Num
             ctxt |b, f| => {
-                Term::val((1..=int(&b)).filter(|&n| bln(&ctxt.evaluate(&term!([f] [:n])))).count() as u32)
+                let count = (1..=int(&b)?).try_fold(0u32, |count, n| {
+                    Ok::<_, EvalError>(count + eval_bln(ctxt, term!([f] [:n]))? as u32)
+                })?;
+                Term::val(count)
             }
-        };
+        }
+        .memoized();
 
         let to_bool = builtin! {
             Conj => Bool
@@ -291,31 +309,32 @@ impl NumLogic {
 
         let and = builtin! {
             Pred => Conj => Conj
-            |a, b| => Term::val(bln(&a) && bln(&b))
+            |a, b| => Term::val(bln(&a)? && bln(&b)?)
         };
 
         let prime = builtin! {
             Atom => Pred
-            |n| => Term::val(is_prime(int(&n)))
-        };
+            |n| => Term::val(is_prime(int(&n)?))
+        }
+        .memoized();
 
         let divisor = builtin! {
             Atom => Atom => Pred
             |p, q| => {
-                let p = int(&p);
-                let q = int(&q);
+                let p = int(&p)?;
+                let q = int(&q)?;
                 Term::val(p > 1 && q % p == 0)
             }
         };
 
         let eq = builtin! {
             Atom => Atom => Pred
-            |l, r| => Term::val(int(&l) == int(&r))
+            |l, r| => Term::val(int(&l)? == int(&r)?)
         };
 
         let less = builtin! {
             Atom => Atom => Pred
-            |l, r| => Term::val(int(&l) < int(&r))
+            |l, r| => Term::val(int(&l)? < int(&r)?)
         };
 
         vec![
@@ -356,7 +375,7 @@ fn is_prime(n: u32) -> bool {
 
     let mut i = 2;
     while i * i <= n {
-        if n % i == 0 {
+        if n.is_multiple_of(i) {
             return false;
         }
         i += 1;
@@ -458,3 +477,21 @@ impl Display for NumLogicSems {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sigma_overflows() {
+        let ctxt = NumLogic::new(2).context();
+        let sum = |b: u32| term!(sigma [:b] (n -> num (pow n [:3u32])));
+
+        // (1 + ... + b)^2, which first exceeds u32::MAX at b = 362
+        let out = ctxt.evaluate(&sum(361)).and_then(|t| t.get::<u32>());
+        assert_eq!(out, Ok(4_269_446_281));
+
+        let out = ctxt.evaluate(&sum(362));
+        assert_eq!(out.unwrap_err(), EvalError::Overflow);
+    }
+}
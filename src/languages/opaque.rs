@@ -1,4 +1,4 @@
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Debug)]
 pub struct Opaque;
@@ -6,10 +6,10 @@ pub struct Opaque;
 impl Language for Opaque {
     type Semantics = OpaqueSemantics;
 
-    // Max size of `small` terms. (TODO: Make language-dependent)
+    // Default max size of `small` terms (see `MutationPolicy`)
     const SMALL_SIZE: usize = 5;
 
-    // Max size of `large` terms. (TODO: Make language-dependent)
+    // Default max size of `large` terms (see `MutationPolicy`)
     const LARGE_SIZE: usize = 8;
 
     fn context(&self) -> Context {
@@ -17,12 +17,12 @@ impl Language for Opaque {
 
         let plus = builtin!(
             N => N => N
-            |x, y| => Term::val(int(&x)+int(&y))
+            |x, y| => Term::val(int(&x)?.checked_add(int(&y)?).ok_or(EvalError::Overflow)?)
         );
 
         let mult = builtin!(
             N => N => N
-            |x, y| => Term::val(int(&x)*int(&y))
+            |x, y| => Term::val(int(&x)?.checked_mul(int(&y)?).ok_or(EvalError::Overflow)?)
         );
 
         let one = builtin!(
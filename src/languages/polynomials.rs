@@ -1,7 +1,7 @@
 use std::fmt::Display;
 use std::rc::Rc;
 
-use kolmogorov::*;
+use crate::*;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Polynomials;
@@ -25,12 +25,12 @@ impl Language for Polynomials {
     fn context(&self) -> Context {
         let plus = builtin!(
             N => N => N
-            |x, y| => Term::val(x.get::<i32>().wrapping_add(y.get::<i32>()))
+            |x, y| => Term::val(x.get::<i32>()?.wrapping_add(y.get::<i32>()?))
         );
 
         let mult = builtin!(
             N => N => N
-            |x, y| => Term::val(x.get::<i32>().wrapping_mul(y.get::<i32>()))
+            |x, y| => Term::val(x.get::<i32>()?.wrapping_mul(y.get::<i32>()?))
         );
 
         let one = builtin!(
@@ -52,20 +52,20 @@ impl Language for Polynomials {
 
     fn svar(&self, v: Identifier, _: &Type) -> Analysis<Self> {
         use Identifier::*;
-        match v {
-            Name("plus") => {
+        match v.name() {
+            Some("plus") => {
                 let a = Uuid(0);
                 let b = Uuid(1);
                 Canonical(PolySem(vec![a, b], Sum::from([a, b])))
             }
-            Name("mult") => {
+            Some("mult") => {
                 let a = Uuid(0);
                 let b = Uuid(1);
                 Canonical(PolySem(vec![a, b], Product::from([a, b]).into()))
             }
-            Name("zero") => Canonical(PolySem::num(0)),
-            Name("one") => Canonical(PolySem::num(1)),
-            v => Canonical(PolySem::var(v)),
+            Some("zero") => Canonical(PolySem::num(0)),
+            Some("one") => Canonical(PolySem::num(1)),
+            _ => Canonical(PolySem::var(v)),
         }
     }
 
@@ -167,7 +167,7 @@ impl Sum {
     pub fn to_pow(&self, n: usize) -> Sum {
         if n == 0 {
             Sum::from(1)
-        } else if n % 2 == 0 {
+        } else if n.is_multiple_of(2) {
             let a = &self.to_pow(n / 2);
             a.mul(a)
         } else {
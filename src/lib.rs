@@ -1,10 +1,17 @@
+pub mod budget;
 pub mod generate;
 pub mod lambda;
+pub mod languages;
+pub mod oeis;
+pub mod results;
 pub mod search;
 pub mod types;
 
+pub use budget::*;
 pub use generate::*;
 pub use lambda::*;
+pub use oeis::*;
+pub use results::*;
 pub use search::*;
 pub use types::*;
 
@@ -1,4 +1,4 @@
-use std::ops::Range;
+// Loading of OEIS sequences (from `data/`) for the binaries
 
 use rustc_hash::FxHashMap as HashMap;
 
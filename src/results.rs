@@ -0,0 +1,193 @@
+// Machine-readable results of synthesis runs, one JSON object per line (JSON Lines),
+// so runs can be diffed & solve rates aggregated across configurations.
+
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::io::{BufRead, Error, ErrorKind, Write};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub id: String, // What was synthesized (e.g. an OEIS sequence, "A000045")
+    pub method: Method,
+    pub solved: bool,
+    // The solution, or best term found (None if an enumerative search found nothing)
+    pub term: Option<String>,
+    pub size: Option<usize>,
+    pub semantics: Option<String>,
+    pub num_correct: Option<usize>, // Examples satisfied (Metropolis only)
+    pub score: Option<f64>,         // None if solved (or enumerative)
+    pub iterations: usize,          // Metropolis iterations, or terms enumerated
+    pub time: f64,                  // Seconds
+    pub stopped: Option<StopReason>,
+    pub params: Option<SynthesisParameters>, // Metropolis only
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    Metropolis,
+    Enumerative,
+}
+
+impl RunRecord {
+    // `found` is the solution (& its semantics), if the search found one
+    pub fn enumerative(
+        id: impl Into<String>,
+        found: Option<(&Term, &dyn Display)>,
+        terms: usize,
+        time: f64,
+        stopped: Option<StopReason>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            method: Method::Enumerative,
+            solved: found.is_some(),
+            term: found.map(|(term, _)| term.to_string()),
+            size: found.map(|(term, _)| term.size()),
+            semantics: found.map(|(_, semantics)| semantics.to_string()),
+            num_correct: None,
+            score: None,
+            iterations: terms,
+            time,
+            stopped,
+            params: None,
+            seed: None,
+        }
+    }
+
+    // Writes the record as a single line
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+        writeln!(writer)
+    }
+
+    // Reads every record, skipping blank lines
+    pub fn read_all<R: BufRead>(reader: R) -> std::io::Result<Vec<Self>> {
+        let mut records = vec![];
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record = serde_json::from_str(&line).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+            })?;
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+}
+
+impl<L: Language> MetropolisOutput<L> {
+    pub fn record(&self, id: impl Into<String>) -> RunRecord {
+        RunRecord {
+            id: id.into(),
+            method: Method::Metropolis,
+            solved: self.score.is_none(),
+            term: Some(self.term.to_string()),
+            size: Some(self.term.size()),
+            semantics: Some(self.analysis.to_string()),
+            num_correct: Some(self.num_correct),
+            score: self.score,
+            iterations: self.iterations,
+            time: self.time,
+            stopped: self.stopped,
+            params: Some(self.settings.clone()),
+            seed: self.seed,
+        }
+    }
+}
+
+// Fraction of runs which were solved (None if there are none)
+pub fn solve_rate(records: &[RunRecord]) -> Option<f64> {
+    let solved = records.iter().filter(|r| r.solved).count();
+    (!records.is_empty()).then(|| solved as f64 / records.len() as f64)
+}
+
+// Ids of the solved runs, e.g. to compare which problems two runs solved
+pub fn solved_ids(records: &[RunRecord]) -> BTreeSet<&str> {
+    records
+        .iter()
+        .filter(|r| r.solved)
+        .map(|r| r.id.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metropolis(id: &str, solved: bool) -> RunRecord {
+        RunRecord {
+            id: id.into(),
+            method: Method::Metropolis,
+            solved,
+            term: Some("x -> plus x one".into()),
+            size: Some(6),
+            semantics: Some("x + 1".into()),
+            num_correct: Some(3),
+            score: (!solved).then_some(0.25),
+            iterations: 1000,
+            time: 1.5,
+            stopped: (!solved).then_some(StopReason::Deadline),
+            params: Some(SynthesisParameters::default()),
+            seed: Some(7),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let term = term!(x -> x);
+        let records = vec![
+            metropolis("A000001", true),
+            metropolis("A000002", false),
+            RunRecord::enumerative("A000003", Some((&term, &"x")), 12, 0.5, None),
+            RunRecord::enumerative("A000004", None, 40, 2., Some(StopReason::Terms)),
+        ];
+
+        let mut buffer = vec![];
+        for record in &records {
+            record.write_to(&mut buffer).unwrap();
+        }
+        // One record per line, with blank lines skipped
+        buffer.extend(b"\n\n");
+        assert_eq!(buffer.iter().filter(|&&b| b == b'\n').count(), 6);
+
+        assert_eq!(RunRecord::read_all(&buffer[..]).unwrap(), records);
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        let mut buffer = vec![];
+        metropolis("A000001", true).write_to(&mut buffer).unwrap();
+        buffer.extend(b"{\"id\": 3}\n");
+
+        let err = RunRecord::read_all(&buffer[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn solve_rates() {
+        assert_eq!(solve_rate(&[]), None);
+
+        let records = [
+            metropolis("A000001", true),
+            metropolis("A000002", false),
+            metropolis("A000003", true),
+            metropolis("A000004", false),
+        ];
+        assert_eq!(solve_rate(&records), Some(0.5));
+        assert_eq!(
+            solved_ids(&records).into_iter().collect::<Vec<_>>(),
+            ["A000001", "A000003"]
+        );
+    }
+}
@@ -15,10 +15,10 @@ where
 pub trait Language: Sized + Clone + Debug {
     type Semantics: Semantics + Sized;
 
-    // Max size of `small` terms. (TODO: Make language-dependent)
+    // Default max size of `small` terms (see `MutationPolicy`)
     const SMALL_SIZE: usize = 5;
 
-    // Max size of `large` terms. (TODO: Make language-dependent)
+    // Default max size of `large` terms (see `MutationPolicy`)
     const LARGE_SIZE: usize = 10;
 
     fn context(&self) -> Context;
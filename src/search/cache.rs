@@ -2,9 +2,10 @@ use super::*;
 
 use rustc_hash::FxHashMap as HashMap;
 
-type Search = (Rc<Type>, usize);
+type Search = (Arc<Type>, usize);
 type PathDict = HashMap<Search, SearchResult>;
 type SemanticDict<L> = HashMap<(<L as Language>::Semantics, Type), (Term, usize)>;
+pub type CacheLevel<L> = (PathDict, SemanticDict<L>);
 
 #[derive(Debug, Default, Clone)]
 pub enum SearchResult {
@@ -50,17 +51,27 @@ impl<L: Language> Cache<L> {
         self.consts.pop();
     }
 
-    pub fn prune(&self, targ: &Rc<Type>, size: usize) -> &SearchResult {
+    // Temporarily removes the innermost variable's level, to be restored by `resume_var`
+    pub fn suspend_var(&mut self) -> CacheLevel<L> {
+        (self.paths.pop().unwrap(), self.consts.pop().unwrap())
+    }
+
+    pub fn resume_var(&mut self, (paths, consts): CacheLevel<L>) {
+        self.paths.push(paths);
+        self.consts.push(consts);
+    }
+
+    pub fn prune(&self, targ: &Arc<Type>, size: usize) -> &SearchResult {
         let search = (targ.clone(), size);
 
         self.active().get(&search).unwrap_or(&Unknown)
     }
 
-    pub fn prune_arg(&self, targ: &Rc<Type>, l_ty: &Rc<Type>, size: usize) -> SearchResult {
+    pub fn prune_arg(&self, targ: &Arc<Type>, l_ty: &Arc<Type>, size: usize) -> SearchResult {
         fn core<L: Language>(
             dict: &PathDict,
-            targ: &Rc<Type>,
-            l_ty: &Rc<Type>,
+            targ: &Arc<Type>,
+            l_ty: &Arc<Type>,
             size: usize,
         ) -> SearchResult {
             let done = l_ty == targ;
@@ -106,7 +117,7 @@ impl<L: Language> Cache<L> {
     }
 
     // Returns index of search for logging
-    pub fn begin_search(&mut self, targ: &Rc<Type>, size: usize) -> usize {
+    pub fn begin_search(&mut self, targ: &Arc<Type>, size: usize) -> usize {
         let search = (targ.clone(), size);
 
         self.active_mut().entry(search).or_insert(Unknown);
@@ -116,7 +127,7 @@ impl<L: Language> Cache<L> {
 
     pub fn yield_term(
         &mut self,
-        targ: &Rc<Type>,
+        targ: &Arc<Type>,
         size: usize,
         term: Term,
         analysis: Analysis<L>,
@@ -184,6 +195,17 @@ impl<L: Language> Cache<L> {
         }
     }
 
+    // Minimal known size of each canonical form of type `ty` (outside of any abstraction)
+    pub fn minimal_sizes<'a>(
+        &'a self,
+        ty: &'a Type,
+    ) -> impl Iterator<Item = (&'a L::Semantics, usize)> + 'a {
+        self.consts[0]
+            .iter()
+            .filter(move |((_, t), _)| t == ty)
+            .map(|((sem, _), (_, size))| (sem, *size))
+    }
+
     pub fn active(&self) -> &PathDict {
         self.paths.last().unwrap()
     }
@@ -193,6 +215,12 @@ impl<L: Language> Cache<L> {
     }
 }
 
+impl<L: Language> Default for Cache<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SearchResult {
     //Add to space
     pub fn log(&mut self) {
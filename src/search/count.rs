@@ -0,0 +1,182 @@
+// Counting terms without enumerating them. The counts follow the search nodes
+// exactly: a term of type `targ` is either an abstraction (if `targ` is a
+// function type) or a variable applied to arguments, each of which is again
+// such a term. So the number of terms of each (type, size) in a given scope is
+// computed from those of smaller sizes, with no term ever built.
+//
+// This ignores the language's analyses, so it is exact only for languages
+// which never reject or deduplicate terms (i.e. those with `OpaqueSemantics`).
+// For others, `Counter::approximate` enumerates (deduplicated) terms up to a
+// given size, & combines those counts as above for larger terms. Only terms
+// which are equivalent as a whole (rather than in each part) are overcounted.
+//
+// Counts are kept in their own table rather than in the search `Cache`. The
+// cache only records whether a (type, size) is inhabited & the smallest term
+// per semantics, in levels pushed & popped as the search enters & leaves each
+// abstraction. A count depends on the types of every variable in scope, so
+// keying on those lets counts be reused across scopes (& sampling calls),
+// which the cache's levels cannot do.
+
+use super::*;
+
+use rustc_hash::FxHashMap as HashMap;
+
+// Types of the variables in scope (sorted), target type & size
+type CountKey = (Vec<Arc<Type>>, Arc<Type>, usize);
+
+pub struct Counter<'a, L: Language> {
+    pub(super) search_ctxt: SearchContext<'a, L>,
+    counts: HashMap<CountKey, u128>,
+    // Sizes up to which terms are enumerated rather than counted
+    pub(super) enumerate_up_to: usize,
+}
+
+pub fn count_terms<L: Language>(lang: &L, vars: VarsVec, ty: &Type, size: usize) -> u128 {
+    Counter::new(lang).count(vars, ty, size)
+}
+
+impl<'a, L: Language> Counter<'a, L> {
+    pub fn new(lang: &'a L) -> Self {
+        Self::approximate(lang, 0)
+    }
+
+    pub fn approximate(lang: &'a L, enumerate_up_to: usize) -> Self {
+        let targ = Type::Var(uuid());
+
+        Self {
+            search_ctxt: SearchContext::new(lang, vec![], &targ, Cache::new()),
+            counts: HashMap::default(),
+            enumerate_up_to,
+        }
+    }
+
+    // Number of terms of type `ty` & size `size` which `search` would produce
+    // (exactly, or approximately for approximate counters)
+    pub fn count(&mut self, vars: VarsVec, ty: &Type, size: usize) -> u128 {
+        self.enter(vars, ty);
+        self.count_all(&Arc::new(ty.clone()), size)
+    }
+
+    // Sets the scope for a query about terms of type `ty`
+    pub(super) fn enter(&mut self, vars: VarsVec, ty: &Type) {
+        let ground = ground_types(&self.search_ctxt.ctxt, &vars, ty);
+
+        // The ground types decide which instances of polymorphic builtins are used
+        if ground != self.search_ctxt.ground {
+            self.search_ctxt.ground = ground;
+            self.counts.clear();
+        }
+
+        let mut vgen = self.search_ctxt.ctxt.vgen();
+        for (var, _) in &vars {
+            vgen.retire(*var);
+        }
+
+        self.search_ctxt.vgen = vgen;
+        self.search_ctxt.args = vars;
+    }
+
+    pub(super) fn count_all(&mut self, targ: &Arc<Type>, size: usize) -> u128 {
+        if size == 0 {
+            return 0;
+        }
+
+        let mut scope: Vec<_> = self.search_ctxt.args.iter().map(|(_, t)| t.clone()).collect();
+        scope.sort();
+
+        let key = (scope, targ.clone(), size);
+        if let Some(&count) = self.counts.get(&key) {
+            return count;
+        }
+
+        let count = if size <= self.enumerate_up_to {
+            let search_ctxt = &self.search_ctxt;
+            search(search_ctxt.lang, search_ctxt.args.clone(), targ, size).count() as u128
+        } else {
+            let mut count = 0u128;
+
+            for (_, v_ty) in self.search_ctxt.vars_producing(targ) {
+                let spines = if size == 1 {
+                    (v_ty == *targ) as u128
+                } else {
+                    self.count_args(targ, &v_ty, size - 1)
+                };
+
+                count = count.saturating_add(spines);
+            }
+
+            if let Type::Fun(arg, ret) = &**targ {
+                self.search_ctxt.args.push((uuid(), arg.clone()));
+                let bodies = self.count_all(ret, size - 1);
+                self.search_ctxt.args.pop();
+
+                count = count.saturating_add(bodies);
+            }
+
+            count
+        };
+
+        self.counts.insert(key, count);
+        count
+    }
+
+    // Number of ways to apply a term of type `l_ty` to arguments of total size
+    // `size` (counting each application node) to produce a `targ`
+    pub(super) fn count_args(&mut self, targ: &Arc<Type>, l_ty: &Arc<Type>, size: usize) -> u128 {
+        if l_ty == targ {
+            return (size == 0) as u128;
+        }
+
+        let Type::Fun(arg_ty, ret_ty) = &**l_ty else {
+            return 0;
+        };
+
+        let mut count = 0u128;
+        for arg_size in 1..size {
+            let rest = self.count_args(targ, ret_ty, size - arg_size - 1);
+            if rest == 0 {
+                continue;
+            }
+
+            let args = self.count_all(arg_ty, arg_size);
+            count = count.saturating_add(args.saturating_mul(rest));
+        }
+
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::*;
+
+    fn same_count<L: Language>(lang: &L, ty: Type, sizes: std::ops::RangeInclusive<usize>) {
+        let mut counter = Counter::new(lang);
+
+        for size in sizes {
+            let searched = search(lang, vec![], &ty, size).count() as u128;
+            assert_eq!(count_terms(lang, vec![], &ty, size), searched, "size {}", size);
+            // Reusing a counter across queries gives the same counts
+            assert_eq!(counter.count(vec![], &ty, size), searched, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn counts_match_search() {
+        same_count(&Opaque, ty!(N => N), 1..=9);
+        same_count(&Opaque, ty!(N => N => N), 1..=8);
+        same_count(&FibLang, ty!(N => N), 1..=9);
+        same_count(&Empty, ty!((A => A) => A => A), 1..=10);
+    }
+
+    #[test]
+    fn counts_in_scope() {
+        let vars = vec![("x".into(), Arc::new(ty!(N)))];
+
+        for size in 1..=7 {
+            let searched = search(&Opaque, vars.clone(), &ty!(N), size).count() as u128;
+            assert_eq!(count_terms(&Opaque, vars.clone(), &ty!(N), size), searched);
+        }
+    }
+}
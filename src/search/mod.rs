@@ -5,17 +5,23 @@
 
 mod analysis;
 mod cache;
+mod count;
 mod node;
+mod parallel;
+mod sample;
 mod semantics;
 
 pub use analysis::*;
+pub use count::*;
+pub use parallel::*;
+pub use sample::*;
 pub use semantics::*;
 
 use super::*;
 pub use cache::*;
 use node::*;
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub fn search<'a, L: Language>(
     lang: &'a L,
@@ -33,28 +39,26 @@ pub fn search_with_cache<'a, L: Language>(
     size: usize,
     cache: Cache<L>,
 ) -> Enumerator<'a, L> {
-    let ctxt = lang.context();
-
-    let mut vgen = ctxt.vgen();
-
-    for (var, _) in &vars {
-        vgen.retire(*var);
-    }
+    search_partition(lang, vars, targ, size, Partition::Full, cache)
+}
 
+fn search_partition<'a, L: Language>(
+    lang: &'a L,
+    vars: VarsVec,
+    targ: &Type,
+    size: usize,
+    part: Partition,
+    cache: Cache<L>,
+) -> Enumerator<'a, L> {
     Enumerator {
-        search_ctxt: SearchContext {
-            lang,
-            ctxt,
-            vgen,
-            args: vars,
-            cache,
-        },
+        search_ctxt: SearchContext::new(lang, vars, targ, cache),
         root: Node::All {
-            targ: Rc::new(targ.clone()),
+            targ: Arc::new(targ.clone()),
             size,
             state: None,
             phase: AllPhase::START,
             depth: None,
+            part,
         },
     }
 }
@@ -68,9 +72,16 @@ impl<L:Language> Enumerator<'_, L> {
     pub fn cache(self) -> Cache<L> {
         self.search_ctxt.cache
     }
+
+    // Stops enumerating once `budget` is exhausted, even part way to the next term.
+    // The cache of a stopped search is incomplete, so shouldn't be reused.
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.search_ctxt.budget = budget;
+        self
+    }
 }
 
-pub type VarDecl = (Identifier, Rc<Type>);
+pub type VarDecl = (Identifier, Arc<Type>);
 pub type VarsVec = Vec<VarDecl>;
 
 struct SearchContext<'a, L: Language> {
@@ -80,17 +91,51 @@ struct SearchContext<'a, L: Language> {
     // Variables from abstractions
     args: VarsVec,
     cache: Cache<L>,
+    // Types over which otherwise undetermined type variables are instantiated
+    // (see `ground_types`)
+    ground: Vec<Type>,
+    budget: Budget,
 }
 
-impl<L: Language> SearchContext<'_, L> {
-    fn contains_var_of_type(&self, ty: &Rc<Type>) -> bool {
-        let args = self.args.iter().map(|(_, t)| t);
-        let ctxt = self.ctxt.iter().map(|(_, b)| &b.ty);
+impl<'a, L: Language> SearchContext<'a, L> {
+    fn new(lang: &'a L, vars: VarsVec, targ: &Type, cache: Cache<L>) -> Self {
+        let ctxt = lang.context();
+
+        let mut vgen = ctxt.vgen();
 
-        args.chain(ctxt).any(|v_ty| v_ty == ty)
+        for (var, _) in &vars {
+            vgen.retire(*var);
+        }
+
+        let ground = ground_types(&ctxt, &vars, targ);
+
+        SearchContext {
+            lang,
+            ctxt,
+            vgen,
+            args: vars,
+            cache,
+            ground,
+            budget: Budget::default(),
+        }
     }
 
-    fn vars_producing(&mut self, targ: &Rc<Type>) -> VarsVec {
+    // Whether a variable (or some instance of a polymorphic builtin) has type `ty`
+    fn contains_var_of_type(&self, ty: &Arc<Type>) -> bool {
+        let in_args = self.args.iter().any(|(_, v_ty)| v_ty == ty);
+
+        in_args
+            || self.ctxt.iter().any(|(_, b)| {
+                if b.params.is_empty() {
+                    b.ty == *ty
+                } else {
+                    let instance = TypeSub::instantiate(&b.params, &b.ty);
+                    TypeSub::default().unify(&instance, ty).is_some()
+                }
+            })
+    }
+
+    fn vars_producing(&mut self, targ: &Arc<Type>) -> VarsVec {
         fn produces(ty: &Type, target: &Type) -> bool {
             let ret_ty_produces = match ty {
                 Type::Fun(_, r) => produces(r, target),
@@ -100,32 +145,286 @@ impl<L: Language> SearchContext<'_, L> {
             ret_ty_produces || target == ty
         }
 
-        let var_produces = move |(v, ty): (Identifier, &Rc<Type>)| {
-            if produces(ty, targ) {
-                Some((v, ty.clone()))
+        let mut vars = VarsVec::new();
+
+        for (&v, builtin) in self.ctxt.iter() {
+            if builtin.params.is_empty() {
+                if produces(&builtin.ty, targ) {
+                    vars.push((v, builtin.ty.clone()));
+                }
             } else {
-                None
+                let instances = self.instances_producing(&builtin.params, &builtin.ty, targ);
+                vars.extend(instances.into_iter().map(|ty| (v, Arc::new(ty))));
             }
-        };
-
-        let vars = self
-            .ctxt
-            .iter()
-            .map(|(&v, BuiltIn { ty, .. })| (v, ty))
-            .chain(self.args.iter().map(|(v, t)| (*v, t)))
-            .filter_map(var_produces)
-            .collect();
+        }
+
+        // Builtins are hashed by address, so sort them to enumerate in the same order on every run
+        vars.sort_by_key(|(v, _)| *v);
+
+        for (v, ty) in &self.args {
+            if produces(ty, targ) {
+                vars.push((*v, ty.clone()));
+            }
+        }
 
         vars
     }
+
+    // Instances of a polymorphic type which produce `targ` (after any number of
+    // arguments). Type variables not determined by `targ` range over `ground`.
+    fn instances_producing(&self, params: &[Identifier], ty: &Type, targ: &Type) -> Vec<Type> {
+        let ty = TypeSub::instantiate(params, ty);
+
+        let mut instances = vec![];
+        let mut ret = &ty;
+        loop {
+            let mut sub = TypeSub::default();
+            if sub.unify(ret, targ).is_some() {
+                let mut instance = ty.clone();
+                sub.apply(&mut instance);
+                instances.push(instance);
+            }
+
+            match ret {
+                Type::Fun(_, r) => ret = r,
+                _ => break,
+            }
+        }
+
+        let mut ground = vec![];
+        for instance in instances {
+            let free: Vec<_> = instance
+                .vars()
+                .into_iter()
+                .filter(|v| matches!(v, Identifier::Uuid(_)))
+                .collect();
+
+            let mut partial = vec![instance];
+            for v in free {
+                partial = partial
+                    .into_iter()
+                    .flat_map(|instance| {
+                        self.ground.iter().map(move |g| {
+                            let mut sub = TypeSub::default();
+                            sub.unify(&Type::Var(v), g);
+
+                            let mut instance = instance.clone();
+                            sub.apply(&mut instance);
+                            instance
+                        })
+                    })
+                    .collect();
+            }
+
+            ground.extend(partial);
+        }
+
+        // Keeps the order of enumeration independent of the uuids chosen above
+        ground.sort();
+        ground.dedup();
+        ground
+    }
+}
+
+// Every type occurring in the types of the builtins (other than those mentioning
+// quantified variables), variables & target, including compound ones (e.g. `N => N`
+// for a variable of that type). Polymorphic builtins are only instantiated at these,
+// which keeps the instances finite.
+fn ground_types(ctxt: &Context, vars: &VarsVec, targ: &Type) -> Vec<Type> {
+    fn subterms(ty: &Type, params: &[Identifier], types: &mut Vec<Type>) {
+        use Type::*;
+        match ty {
+            Var(v) if params.contains(v) => return,
+            Var(_) => (),
+            Fun(l, r) | Prod(l, r) | Sum(l, r) => {
+                subterms(l, params, types);
+                subterms(r, params, types);
+            }
+            List(t) => subterms(t, params, types),
+        }
+
+        if ty.vars().iter().all(|v| !params.contains(v)) {
+            types.push(ty.clone());
+        }
+    }
+
+    let mut types = vec![];
+
+    for (_, b) in ctxt.iter() {
+        subterms(&b.ty, &b.params, &mut types);
+    }
+
+    for (_, ty) in vars {
+        subterms(ty, &[], &mut types);
+    }
+
+    subterms(targ, &[], &mut types);
+
+    types.sort();
+    types.dedup();
+    types
 }
 
 impl<L: Language> Iterator for Enumerator<'_, L> {
     type Item = (Term, Analysis<L>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.root
-            .next(&mut self.search_ctxt)
-            .map(|(t, a)| (t.deep_clone(), a))
+        if self.search_ctxt.budget.check().is_some() {
+            return None;
+        }
+
+        let (term, analysis) = self.root.next(&mut self.search_ctxt)?;
+        self.search_ctxt.budget.charge_term();
+
+        Some((term.deep_clone(), analysis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::Opaque;
+
+    // `id` & `fst` are polymorphic, so each is enumerated at several types
+    #[derive(Clone, Debug)]
+    struct Generic;
+
+    impl Language for Generic {
+        type Semantics = OpaqueSemantics;
+
+        fn context(&self) -> Context {
+            let id = builtin! {
+                forall a.
+                a => a
+                |x| => x.clone()
+            };
+
+            let fst = builtin! {
+                forall a b.
+                (a, b) => a
+                |p| => p.get::<Pair>()?.0.to_term()
+            };
+
+            let zero = builtin!(
+                N
+                | | => Term::val(0i32)
+            );
+
+            let yes = builtin!(
+                Bool
+                | | => Term::val(true)
+            );
+
+            let pred = builtin!(
+                N => N
+                |x| => Term::val(x.get::<i32>()? - 1)
+            );
+
+            let p = builtin!(
+                (N, N => N)
+                | | => Term::val(Pair(term!(zero).share(), term!(pred).share()))
+            );
+
+            context! { id, fst, zero, yes, pred, p }
+        }
+    }
+
+    fn terms(targ: Type, size: usize) -> Vec<String> {
+        let mut terms: Vec<_> = search(&Generic, vec![], &targ, size)
+            .map(|(term, _)| term.to_string())
+            .collect();
+
+        terms.sort();
+        terms
+    }
+
+    #[test]
+    fn polymorphic_builtins_at_several_types() {
+        assert_eq!(terms(ty!(N), 3), ["fst(p)", "id(zero)", "pred(zero)"]);
+        assert_eq!(terms(ty!(Bool), 3), ["id(yes)"]);
+        assert_eq!(terms(ty!(N => N), 3), ["id(id)", "id(pred)"]);
+
+        for (targ, size) in [(ty!(N), 5), (ty!(Bool), 5), (ty!(N => N), 4)] {
+            let count = count_terms(&Generic, vec![], &targ, size);
+            assert_eq!(count, terms(targ, size).len() as u128);
+        }
+    }
+
+    #[test]
+    fn instances_at_compound_types() {
+        // `fst(p)` needs `b` to be `N => N`, & `id(p)` needs `a` to be `(N, N => N)`
+        let terms = terms(ty!(N), 5);
+        assert!(terms.contains(&"fst(id(p))".to_string()));
+        assert!(terms.contains(&"id(fst(p))".to_string()));
+    }
+
+    #[test]
+    fn instances_are_variables_of_their_type() {
+        let search_ctxt = SearchContext::new(&Generic, vec![], &ty!(N), Cache::new());
+        let has_var = |ty: Type| search_ctxt.contains_var_of_type(&Arc::new(ty));
+
+        assert!(has_var(ty!(N)));
+        assert!(has_var(ty!(N => N)));
+        assert!(has_var(ty!(Bool => Bool)));
+        assert!(has_var(ty!((Bool, N) => Bool)));
+        assert!(!has_var(ty!(Bool => N)));
+        assert!(!has_var(ty!((Bool, N))));
+    }
+
+    // `both` takes two functions, so its arguments are sibling abstractions
+    #[derive(Clone, Debug)]
+    struct HigherOrder;
+
+    impl Language for HigherOrder {
+        type Semantics = OpaqueSemantics;
+
+        fn context(&self) -> Context {
+            let both = builtin!(
+                (N => N) => (N => N) => N
+                ctxt |f, g| => {
+                    let (f, g) = (f.clone(), g.clone());
+                    ctxt.evaluate(&term!([f] ([g] 0)))?
+                }
+            );
+
+            let zero = builtin!(
+                N
+                | | => Term::val(0i32)
+            );
+
+            context! { both, zero }
+        }
+    }
+
+    #[test]
+    fn abstraction_variables_are_scoped() {
+        let ctxt = HigherOrder.context();
+
+        for size in 1..=10 {
+            let terms: Vec<_> = search(&HigherOrder, vec![], &ty!(N), size).collect();
+
+            for (term, _) in &terms {
+                assert!(check(&ctxt, &vec![], term, &ty!(N)).is_ok(), "{}", term);
+            }
+
+            let count = count_terms(&HigherOrder, vec![], &ty!(N), size);
+            assert_eq!(count, terms.len() as u128, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn budgets_stop_searches() {
+        let ty = ty!(N => N);
+
+        let budget = Budget::default().with_max_terms(5);
+        let found = search(&Opaque, vec![], &ty, 7).with_budget(budget.clone());
+        assert_eq!(found.count(), 5);
+        assert_eq!(budget.stop_reason(), Some(StopReason::Terms));
+
+        // Also between terms, not only before each
+        let budget = Budget::default();
+        let mut enumerator = search(&Opaque, vec![], &ty, 7).with_budget(budget.clone());
+        budget.cancel_token().cancel();
+        assert!(enumerator.root.next(&mut enumerator.search_ctxt).is_none());
     }
 }
@@ -1,34 +1,37 @@
 use super::*;
 
-use std::rc::Rc;
+use std::sync::Arc;
 use SearchResult::*;
 
 #[derive(Clone, Debug)]
 pub(super) enum Node<L: Language> {
     All {
-        targ: Rc<Type>,
+        targ: Arc<Type>,
         size: usize,
         phase: AllPhase,
         state: Option<Box<Node<L>>>,
         depth: Option<usize>,
+        part: Partition,
     },
     Abs {
-        targ: Rc<Type>,
+        targ: Arc<Type>,
         size: usize,
         ident: Option<Identifier>,
         state: Option<Box<Node<L>>>,
+        // Set while `ident` is out of scope (see below)
+        scope: Option<Box<Scope<L>>>,
     },
     Var {
-        targ: Rc<Type>,
+        targ: Arc<Type>,
         size: usize,
         vars: VarsVec,
         state: Option<Box<Node<L>>>,
         depth: usize,
     },
     Arg {
-        targ: Rc<Type>,
+        targ: Arc<Type>,
         size: usize,
-        l_ty: Rc<Type>,
+        l_ty: Arc<Type>,
         left: Thunk,
         left_analysis: Analysis<L>,
         res: SearchResult,
@@ -51,6 +54,22 @@ impl AllPhase {
     pub const START: Self = Self::CacheCheck;
 }
 
+// A variable introduced by an `Abs` node, & its level of the cache
+#[derive(Clone, Debug)]
+pub(super) struct Scope<L: Language> {
+    var: VarDecl,
+    cache: CacheLevel<L>,
+}
+
+// Restricts the terms generated by an `All` node, so that a search may be split
+// up (e.g. between threads). Only ever used at the root of a search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Partition {
+    Full,
+    Head(Identifier), // Applications of the given variable
+    Abstraction,
+}
+
 impl<L: Language> Node<L> {
     pub fn next(&mut self, search_ctxt: &mut SearchContext<L>) -> Option<(Term, Analysis<L>)> {
         use Node::*;
@@ -68,6 +87,7 @@ impl<L: Language> Node<L> {
                     phase,
                     state,
                     depth,
+                    part,
                 } => {
                     let size = *size;
 
@@ -97,6 +117,12 @@ impl<L: Language> Node<L> {
                     use AllPhase::*;
                     match phase {
                         CacheCheck => {
+                            // Searches may be long between terms, so also stop here
+                            if search_ctxt.budget.check().is_some() {
+                                *self = Nil;
+                                return None;
+                            }
+
                             match search_ctxt.cache.prune(targ, size) {
                                 Empty => {
                                     *self = Nil;
@@ -108,12 +134,23 @@ impl<L: Language> Node<L> {
                             continue;
                         }
                         Application => {
+                            let mut vars = search_ctxt.vars_producing(targ);
+
                             *phase = Abstraction;
+                            match part {
+                                Partition::Full => (),
+                                Partition::Head(head) => {
+                                    vars.retain(|(v, _)| v == head);
+                                    *phase = Completed;
+                                }
+                                Partition::Abstraction => vars.clear(),
+                            }
+
                             *state = Some(Box::new(Var {
                                 targ: targ.clone(),
                                 size,
                                 state: None,
-                                vars: search_ctxt.vars_producing(targ),
+                                vars,
                                 depth: depth.unwrap(),
                             }))
                         }
@@ -124,6 +161,7 @@ impl<L: Language> Node<L> {
                                 ident: None,
                                 size,
                                 state: None,
+                                scope: None,
                             }))
                         }
                         Completed => {
@@ -139,6 +177,7 @@ impl<L: Language> Node<L> {
                     size,
                     ident,
                     state,
+                    scope,
                 } => {
                     let Type::Fun(arg, ret) = &**targ else {
                         *self = Nil;
@@ -148,8 +187,21 @@ impl<L: Language> Node<L> {
                     let ident = *ident.get_or_insert_with(|| search_ctxt.vgen.small_var());
 
                     if let Some(curr_state) = state {
+                        if let Some(scope) = scope.take() {
+                            search_ctxt.args.push(scope.var);
+                            search_ctxt.cache.resume_var(scope.cache);
+                        }
+
                         return match curr_state.next(search_ctxt) {
                             Some((term, analysis)) => {
+                                // Until asked for the next term, `ident` goes out of scope, as
+                                // the search may move on to siblings of this abstraction (e.g.
+                                // later arguments of an application)
+                                *scope = Some(Box::new(Scope {
+                                    var: search_ctxt.args.pop().unwrap(),
+                                    cache: search_ctxt.cache.suspend_var(),
+                                }));
+
                                 let term = Term::Lam(ident, term.into());
 
                                 let analysis = search_ctxt.lang.slam(ident, analysis, targ);
@@ -162,17 +214,18 @@ impl<L: Language> Node<L> {
                         };
                     };
 
-                    search_ctxt.args.push((ident, arg.clone()));
-
                     let is_new = !search_ctxt.contains_var_of_type(arg);
                     search_ctxt.cache.intro_var(is_new);
 
+                    search_ctxt.args.push((ident, arg.clone()));
+
                     *state = Some(Box::new(All {
                         targ: ret.clone(),
                         size: *size - 1,
                         state: None,
                         phase: AllPhase::START,
                         depth: None,
+                        part: Partition::Full,
                     }));
                 }
 
@@ -292,6 +345,7 @@ impl<L: Language> Node<L> {
                                 state: None,
                                 phase: AllPhase::START,
                                 depth: None,
+                                part: Partition::Full,
                             }));
 
                             arg_state.as_mut().unwrap()
@@ -331,6 +385,7 @@ impl<L: Language> Node<L> {
                             state: None,
                             targ: arg_targ.clone(),
                             depth: arg_depth,
+                            part: Partition::Full,
                         };
                     };
 
@@ -375,13 +430,16 @@ impl<L: Language> Node<L> {
             Abs {
                 state: Some(state),
                 ident,
+                scope,
                 ..
             } => {
                 state.exit(search_ctxt);
                 let ident = ident.unwrap();
-                search_ctxt.args.pop().unwrap();
+                if scope.take().is_none() {
+                    search_ctxt.args.pop().unwrap();
+                    search_ctxt.cache.elim_var();
+                }
                 search_ctxt.vgen.freshen(ident);
-                search_ctxt.cache.elim_var();
             }
             Var {
                 state: Some(state), ..
@@ -406,11 +464,10 @@ impl<L: Language> Node<L> {
 use std::fmt::*;
 impl<L: Language + Debug> Display for Node<L> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        static mut DISP_DEPTH: usize = 0;
-        let indent = 4 * unsafe {
-            DISP_DEPTH += 1;
-            DISP_DEPTH - 1
-        };
+        thread_local! {
+            static DISP_DEPTH: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+        let indent = 4 * DISP_DEPTH.replace(DISP_DEPTH.get() + 1);
 
         use Node::*;
         let out = match self {
@@ -441,6 +498,7 @@ impl<L: Language + Debug> Display for Node<L> {
                 size,
                 ident,
                 state,
+                ..
             } => {
                 write!(
                     f,
@@ -516,7 +574,7 @@ impl<L: Language + Debug> Display for Node<L> {
             Nil => writeln!(f, "{:indent$}Nil", "", indent = indent),
         };
 
-        unsafe { DISP_DEPTH -= 1 };
+        DISP_DEPTH.set(DISP_DEPTH.get() - 1);
         out
     }
 }
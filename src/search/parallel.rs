@@ -0,0 +1,225 @@
+// Parallel enumeration. The root of a search is split into partitions (one per
+// head variable, plus one for abstractions) which are enumerated independently
+// by a pool of scoped threads, each with its own `Cache`.
+//
+// Semantic deduplication between partitions happens when merging: partitions
+// are merged in the order a sequential search would visit them, keeping only
+// the first term with each canonical form, and dropping terms whose canonical
+// form any partition found a smaller representative of.
+//
+// Every partition draws on the given `Budget`. Once it is exhausted, the terms
+// found so far are returned (see `Budget::stop_reason`).
+
+use super::*;
+
+use rustc_hash::FxHashSet as HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub fn par_search<L>(
+    lang: &L,
+    vars: VarsVec,
+    targ: &Type,
+    size: usize,
+    budget: &Budget,
+) -> Vec<(SharedTerm, Analysis<L>)>
+where
+    L: Language + Sync,
+    L::Semantics: Send,
+{
+    par_search_filter_map(
+        lang,
+        vars,
+        targ,
+        size,
+        available_threads(),
+        budget,
+        |term, analysis| Some((term.share(), analysis.clone())),
+    )
+}
+
+// Applies `f` to each generated term on the worker threads, so that expensive
+// per-term work (e.g. evaluation) is parallelized along with the search.
+pub fn par_search_filter_map<L, T, F>(
+    lang: &L,
+    vars: VarsVec,
+    targ: &Type,
+    size: usize,
+    threads: usize,
+    budget: &Budget,
+    f: F,
+) -> Vec<T>
+where
+    L: Language + Sync,
+    L::Semantics: Send,
+    T: Send,
+    F: Fn(&Term, &Analysis<L>) -> Option<T> + Sync,
+{
+    if size == 0 {
+        return vec![];
+    }
+
+    let parts = partitions(lang, &vars, targ);
+
+    let next = AtomicUsize::new(0);
+    let results: Vec<_> = parts.iter().map(|_| Mutex::new(None)).collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, parts.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(part) = parts.get(i) else {
+                    break;
+                };
+
+                let part = part.clone();
+                let result = run_partition(lang, vars.clone(), targ, size, part, budget, &f);
+                *results[i].lock().unwrap() = Some(result);
+            });
+        }
+    });
+
+    let results: Vec<_> = results
+        .into_iter()
+        .map(|r| r.into_inner().unwrap().unwrap())
+        .collect();
+
+    let mut smaller = HashSet::default();
+    for result in &results {
+        smaller.extend(result.smaller.iter().cloned());
+    }
+
+    let mut seen = HashSet::default();
+    let mut output = vec![];
+    for result in results {
+        for (analysis, out) in result.items {
+            if let Analysis::Canonical(sem) = analysis {
+                if smaller.contains(&sem) || !seen.insert(sem) {
+                    continue;
+                }
+            }
+
+            output.extend(out);
+        }
+    }
+
+    output
+}
+
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+struct PartitionResult<L: Language, T> {
+    // Every generated term's analysis is kept (even if `f` discards the term)
+    // so that deduplication matches that of a sequential search.
+    items: Vec<(Analysis<L>, Option<T>)>,
+    // Canonical forms of type `targ` found below the requested size
+    smaller: HashSet<L::Semantics>,
+}
+
+fn run_partition<L, T, F>(
+    lang: &L,
+    vars: VarsVec,
+    targ: &Type,
+    size: usize,
+    part: Partition,
+    budget: &Budget,
+    f: &F,
+) -> PartitionResult<L, T>
+where
+    L: Language,
+    F: Fn(&Term, &Analysis<L>) -> Option<T>,
+{
+    let mut enumerator =
+        search_partition(lang, vars, targ, size, part, Cache::new()).with_budget(budget.clone());
+
+    let items = enumerator
+        .by_ref()
+        .map(|(term, analysis)| {
+            let out = f(&term, &analysis);
+            (analysis, out)
+        })
+        .collect();
+
+    let cache = enumerator.cache();
+    let smaller = cache
+        .minimal_sizes(targ)
+        .filter(|(_, s)| *s < size)
+        .map(|(sem, _)| sem.clone())
+        .collect();
+
+    PartitionResult { items, smaller }
+}
+
+// Partitions in the order a sequential search visits them (it tries head
+// variables from the back of the list first, then abstractions).
+fn partitions<L: Language>(lang: &L, vars: &VarsVec, targ: &Type) -> Vec<Partition> {
+    let mut enumerator = search(lang, vars.clone(), targ, 0);
+    let heads = enumerator
+        .search_ctxt
+        .vars_producing(&Arc::new(targ.clone()));
+
+    let mut seen = HashSet::default();
+    let mut parts: Vec<_> = heads
+        .into_iter()
+        .rev()
+        .filter(|(v, _)| seen.insert(*v))
+        .map(|(v, _)| Partition::Head(v))
+        .collect();
+
+    if let Type::Fun(_, _) = targ {
+        parts.push(Partition::Abstraction);
+    }
+
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::*;
+
+    fn same_terms<L: Language + Sync>(lang: &L, ty: Type, sizes: std::ops::RangeInclusive<usize>)
+    where
+        L::Semantics: Send,
+    {
+        for size in sizes {
+            let mut sequential: Vec<_> = search(lang, vec![], &ty, size)
+                .map(|(term, _)| term.to_string())
+                .collect();
+            let mut parallel: Vec<_> = par_search(lang, vec![], &ty, size, &Budget::default())
+                .into_iter()
+                .map(|(term, _)| term.to_string())
+                .collect();
+
+            sequential.sort();
+            parallel.sort();
+            assert_eq!(sequential, parallel, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn matches_sequential_search() {
+        // Without semantic deduplication, every term is kept
+        same_terms(&Opaque, ty!(N => N), 1..=8);
+        // With it, partitions must agree on which representative to keep
+        same_terms(&Polynomials, ty!(N => N => N), 1..=12);
+        same_terms(&NumLogic::new(2), ty!(Var => Bool), 1..=16);
+    }
+
+    #[test]
+    fn budgets_stop_searches() {
+        let ty = ty!(N => N);
+
+        let budget = Budget::default();
+        budget.cancel_token().cancel();
+        assert!(par_search(&Opaque, vec![], &ty, 7, &budget).is_empty());
+        assert_eq!(budget.stop_reason(), Some(StopReason::Cancelled));
+
+        // Shared by every partition
+        let budget = Budget::default().with_max_terms(3);
+        let found = par_search_filter_map(&Opaque, vec![], &ty, 7, 1, &budget, |_, _| Some(()));
+        assert_eq!(found.len(), 3);
+    }
+}
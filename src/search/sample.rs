@@ -0,0 +1,186 @@
+// Uniform sampling of terms by type & size, using the counts of `Counter`.
+// Each choice (of head variable, abstraction, or the sizes of arguments) is
+// made with probability proportional to the number of terms it leads to, so
+// every term is drawn with probability exactly 1 / `Counter::count`.
+//
+// For approximate counters, small terms are drawn from the enumerated ones,
+// so for languages which deduplicate terms, larger samples may be malformed
+// (& are then rejected) or equivalent to others.
+
+use super::*;
+
+use rand::Rng;
+
+pub fn sample_uniform<L: Language, R: Rng + ?Sized>(
+    lang: &L,
+    vars: VarsVec,
+    ty: &Type,
+    size: usize,
+    rng: &mut R,
+) -> Option<(Term, Analysis<L>)> {
+    Counter::new(lang).sample(vars, ty, size, rng)
+}
+
+impl<L: Language> Counter<'_, L> {
+    // `None` if there are no terms of this type & size (or the sample was malformed)
+    pub fn sample<R: Rng + ?Sized>(
+        &mut self,
+        vars: VarsVec,
+        ty: &Type,
+        size: usize,
+        rng: &mut R,
+    ) -> Option<(Term, Analysis<L>)> {
+        self.enter(vars, ty);
+        self.sample_all(&Arc::new(ty.clone()), size, rng)
+    }
+
+    fn sample_all<R: Rng + ?Sized>(
+        &mut self,
+        targ: &Arc<Type>,
+        size: usize,
+        rng: &mut R,
+    ) -> Option<(Term, Analysis<L>)> {
+        let total = self.count_all(targ, size);
+        if total == 0 {
+            return None;
+        }
+
+        let mut choice = rng.gen_range(0..total);
+
+        if size <= self.enumerate_up_to {
+            let search_ctxt = &self.search_ctxt;
+            let mut terms = search(search_ctxt.lang, search_ctxt.args.clone(), targ, size);
+            return terms.nth(choice as usize);
+        }
+
+        for (var, v_ty) in self.search_ctxt.vars_producing(targ) {
+            let spines = if size == 1 {
+                (v_ty == *targ) as u128
+            } else {
+                self.count_args(targ, &v_ty, size - 1)
+            };
+
+            if choice >= spines {
+                choice -= spines;
+                continue;
+            }
+
+            let analysis = self.search_ctxt.lang.svar(var, &v_ty);
+            return self.sample_args(targ, Term::Var(var), analysis, &v_ty, size - 1, rng);
+        }
+
+        let Type::Fun(arg, ret) = &**targ else {
+            unreachable!()
+        };
+
+        let ident = self.search_ctxt.vgen.small_var();
+
+        self.search_ctxt.args.push((ident, arg.clone()));
+        let body = self.sample_all(ret, size - 1, rng);
+        self.search_ctxt.args.pop();
+        self.search_ctxt.vgen.freshen(ident);
+
+        let (body, analysis) = body?;
+        let analysis = self.search_ctxt.lang.slam(ident, analysis, targ);
+
+        (!analysis.malformed()).then(|| (Term::Lam(ident, body.into()), analysis))
+    }
+
+    // Applies `left` (of type `l_ty`) to random arguments of total size `size`
+    fn sample_args<R: Rng + ?Sized>(
+        &mut self,
+        targ: &Arc<Type>,
+        left: Term,
+        left_analysis: Analysis<L>,
+        l_ty: &Arc<Type>,
+        size: usize,
+        rng: &mut R,
+    ) -> Option<(Term, Analysis<L>)> {
+        if left_analysis.malformed() {
+            return None;
+        }
+
+        if size == 0 {
+            return Some((left, left_analysis));
+        }
+
+        let Type::Fun(arg_ty, ret_ty) = &**l_ty else {
+            unreachable!()
+        };
+
+        let mut choice = rng.gen_range(0..self.count_args(targ, l_ty, size));
+
+        for arg_size in 1..size {
+            let rest = self.count_args(targ, ret_ty, size - arg_size - 1);
+            let ways = self.count_all(arg_ty, arg_size).saturating_mul(rest);
+
+            if choice >= ways {
+                choice -= ways;
+                continue;
+            }
+
+            let (arg, arg_analysis) = self.sample_all(arg_ty, arg_size, rng)?;
+
+            let analysis = self.search_ctxt.lang.sapp(left_analysis, arg_analysis, targ);
+            let left = Term::App(left.into(), arg.into());
+
+            return self.sample_args(targ, left, analysis, ret_ty, size - arg_size - 1, rng);
+        }
+
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::languages::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    // Draws `per_term` samples per term, & checks each term's share is near uniform
+    fn uniform<L: Language>(mut counter: Counter<L>, ty: Type, size: usize) {
+        let per_term = 200;
+        let total = counter.count(vec![], &ty, size) as usize;
+        assert!(total > 1);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut seen = HashMap::new();
+        for _ in 0..total * per_term {
+            let (term, _) = counter.sample(vec![], &ty, size, &mut rng).unwrap();
+            *seen.entry(term.to_string()).or_insert(0usize) += 1;
+        }
+
+        assert_eq!(seen.len(), total);
+        for (term, times) in seen {
+            // Within about 6 standard deviations
+            assert!(times.abs_diff(per_term) < per_term * 2 / 5, "{}: {}", term, times);
+        }
+    }
+
+    #[test]
+    fn samples_are_uniform() {
+        uniform(Counter::new(&Opaque), ty!(N => N), 7);
+        uniform(Counter::new(&FibLang), ty!(N => N), 6);
+    }
+
+    // Small sizes are drawn from `search`, rather than built choice by choice
+    #[test]
+    fn enumerated_samples_are_uniform() {
+        uniform(Counter::approximate(&Opaque, 7), ty!(N => N), 7);
+        uniform(Counter::approximate(&FibLang, 6), ty!(N => N), 6);
+    }
+
+    #[test]
+    fn many_abstractions() {
+        let arity = 40;
+        let a = Arc::new(ty!(A));
+        let ty = (0..arity).fold(a.clone(), |ret, _| Arc::new(Type::Fun(a.clone(), ret)));
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let (term, _) = sample_uniform(&Empty, vec![], &ty, arity + 1, &mut rng).unwrap();
+        check(&Empty.context(), &vec![], &term, &ty).unwrap();
+    }
+}
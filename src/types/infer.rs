@@ -0,0 +1,197 @@
+// Type inference for terms, by unification (see `TypeSub`). Literal values
+// carry no type, so they are given whatever type their context requires.
+
+use super::*;
+
+use rustc_hash::FxHashMap as HashMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TypeError {
+    Undeclared(Identifier),
+    NotAFunction {
+        term: String,
+        ty: Type,
+    },
+    Mismatch {
+        term: String,
+        expected: Type,
+        found: Type,
+    },
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use TypeError::*;
+        match self {
+            Undeclared(v) => write!(f, "undeclared variable `{}`", v),
+            NotAFunction { term, ty } => {
+                write!(f, "`{}` has type `{}` and cannot be applied", term, ty)
+            }
+            Mismatch {
+                term,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` was expected to have type `{}`, but has type `{}`",
+                term, expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TypeError {}
+
+pub type TypeResult<T> = std::result::Result<T, TypeError>;
+
+// Type, size & the variables in scope of a subterm
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub size: usize,
+    pub ty: Type,
+    pub decls: VarsVec,
+}
+
+// Keyed by address, as subterms may be shared through thunks
+pub type Annotations = HashMap<*const Term, Annotation>;
+
+pub fn infer(ctxt: &Context, vars: &VarsVec, term: &Term) -> TypeResult<Type> {
+    let mut inference = Inference::new(ctxt, false);
+    let (mut ty, _) = inference.infer(term, &mut vars.clone())?;
+    inference.sub.apply(&mut ty);
+    Ok(ty)
+}
+
+pub fn check(ctxt: &Context, vars: &VarsVec, term: &Term, ty: &Type) -> TypeResult<()> {
+    let mut inference = Inference::new(ctxt, false);
+    inference.check(term, &mut vars.clone(), ty)?;
+    Ok(())
+}
+
+// Checks `term` against `ty`, annotating each subterm
+pub fn annotate(
+    ctxt: &Context,
+    vars: &VarsVec,
+    term: &Term,
+    ty: &Type,
+) -> TypeResult<Annotations> {
+    let mut inference = Inference::new(ctxt, true);
+    inference.check(term, &mut vars.clone(), ty)?;
+
+    let Inference { sub, mut notes, .. } = inference;
+    for note in notes.values_mut() {
+        sub.apply(&mut note.ty);
+        for (_, ty) in &mut note.decls {
+            sub.apply(Arc::make_mut(ty));
+        }
+    }
+
+    Ok(notes)
+}
+
+struct Inference<'a> {
+    ctxt: &'a Context,
+    sub: TypeSub,
+    record: bool,
+    notes: Annotations,
+}
+
+impl<'a> Inference<'a> {
+    fn new(ctxt: &'a Context, record: bool) -> Self {
+        Self {
+            ctxt,
+            sub: TypeSub::default(),
+            record,
+            notes: Annotations::default(),
+        }
+    }
+
+    // Returns the size of `term`
+    fn check(&mut self, term: &Term, decls: &mut VarsVec, ty: &Type) -> TypeResult<usize> {
+        let (found, size) = self.infer(term, decls)?;
+        self.unify(term, ty, &found)?;
+        Ok(size)
+    }
+
+    fn unify(&mut self, term: &Term, expected: &Type, found: &Type) -> TypeResult<()> {
+        if self.sub.unify(expected, found).is_some() {
+            return Ok(());
+        }
+
+        let mut expected = expected.clone();
+        let mut found = found.clone();
+        self.sub.apply(&mut expected);
+        self.sub.apply(&mut found);
+
+        Err(TypeError::Mismatch {
+            term: term.to_string(),
+            expected,
+            found,
+        })
+    }
+
+    fn infer(&mut self, term: &Term, decls: &mut VarsVec) -> TypeResult<(Type, usize)> {
+        use Term::*;
+        let (ty, size) = match term {
+            Ref(r) => self.infer(&r.borrow(), decls)?,
+            Val(_) => (Type::fresh(), 1),
+            Var(v) => {
+                let ty = if let Some((_, ty)) = decls.iter().rev().find(|(d, _)| d == v) {
+                    (**ty).clone()
+                } else if let Some(builtin) = self.ctxt.get(*v) {
+                    TypeSub::instantiate(&builtin.params, &builtin.ty)
+                } else {
+                    return Err(TypeError::Undeclared(*v));
+                };
+
+                (ty, 1)
+            }
+            Lam(v, b) => {
+                let arg = Arc::new(Type::fresh());
+
+                decls.push((*v, arg.clone()));
+                let body = self.infer(b, decls);
+                decls.pop();
+
+                let (ret, size) = body?;
+                (Type::Fun(arg, ret.into()), 1 + size)
+            }
+            App(l, r) => {
+                let l = l.borrow();
+                let (mut f, l_size) = self.infer(&l, decls)?;
+                self.sub.apply(&mut f);
+
+                let (arg, ret) = match f {
+                    Type::Fun(arg, ret) => (arg, ret),
+                    Type::Var(Identifier::Uuid(_)) => {
+                        let (arg, ret) = (Type::fresh(), Type::fresh());
+                        let fun = Type::Fun(arg.clone().into(), ret.clone().into());
+                        self.unify(&l, &fun, &f)?;
+                        (arg.into(), ret.into())
+                    }
+                    ty => {
+                        return Err(TypeError::NotAFunction {
+                            term: l.to_string(),
+                            ty,
+                        })
+                    }
+                };
+
+                let r_size = self.check(&r.borrow(), decls, &arg)?;
+                ((*ret).clone(), 1 + l_size + r_size)
+            }
+        };
+
+        if self.record {
+            let ptr = term as *const Term;
+            self.notes.entry(ptr).or_insert_with(|| Annotation {
+                size,
+                ty: ty.clone(),
+                decls: decls.clone(),
+            });
+        }
+
+        Ok((ty, size))
+    }
+}
@@ -1,18 +1,28 @@
+pub mod infer;
 pub mod subs;
+pub use infer::*;
 pub use subs::*;
 
 use super::*;
 
 use rustc_hash::FxHashSet as HashSet;
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Type {
     Var(Identifier),
-    Fun(Rc<Type>, Rc<Type>),
+    Fun(Arc<Type>, Arc<Type>),
+    Prod(Arc<Type>, Arc<Type>),
+    Sum(Arc<Type>, Arc<Type>),
+    List(Arc<Type>),
 }
 
 impl Type {
+    // A new type variable, which may be substituted during unification
+    pub fn fresh() -> Self {
+        Self::Var(uuid())
+    }
+
     pub fn vars(&self) -> HashSet<Identifier> {
         use Type::*;
         match self {
@@ -21,13 +31,14 @@ impl Type {
                 set.insert(*v);
                 set
             }
-            Fun(l, r) => {
+            Fun(l, r) | Prod(l, r) | Sum(l, r) => {
                 let mut vars = l.vars();
                 for v in r.vars() {
                     vars.insert(v);
                 }
                 vars
             }
+            List(t) => t.vars(),
         }
     }
 }
@@ -48,6 +59,9 @@ impl Display for Type {
                 write!(f, "=>{}", r)?;
                 write!(f, ")")
             }
+            Prod(x, y) => write!(f, "({}, {})", x, y),
+            Sum(x, y) => write!(f, "({} | {})", x, y),
+            List(t) => write!(f, "(List {})", t),
         }
     }
 }
@@ -63,3 +77,17 @@ impl From<Identifier> for Type {
         Self::Var(ident)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        assert_eq!(ty!(N => N => N).to_string(), "(N=>N=>N)");
+        assert_eq!(ty!((N, N => Bool)).to_string(), "(N, (N=>Bool))");
+        assert_eq!(ty!((N | Bool)).to_string(), "(N | Bool)");
+        assert_eq!(ty!(List(N, Bool)).to_string(), "(List (N, Bool))");
+        assert_eq!(ty!((List N) => N).to_string(), "((List N)=>N)");
+    }
+}
@@ -1,74 +1,64 @@
 use super::*;
 use rustc_hash::FxHashMap as HashMap;
 
+// Only uuid type variables (see `Type::fresh`) may be substituted: named type
+// variables are the base types of a language (e.g. `N`, `Bool`), which unify
+// with nothing but themselves.
 #[derive(Default, Debug)]
 pub struct TypeSub {
     dict: HashMap<Identifier, Type>,
 }
 
 impl TypeSub {
+    // On failure, the substitution is left unchanged
     pub fn unify(&mut self, lhs: &Type, rhs: &Type) -> Option<Type> {
         use Type::*;
 
-        type RefSub<'a> = HashMap<Identifier, &'a Type>;
+        let mut news = HashMap::default();
+        let mut stack = vec![(lhs.clone(), rhs.clone())];
 
-        fn contains(sub: &RefSub, s: Identifier, t: &Type) -> bool {
-            match t {
-                Var(v) if *v == s => true,
-                Var(v) => {
-                    if let Some(t) = sub.get(v) {
-                        contains(sub, s, t)
-                    } else {
-                        false
-                    }
-                }
-                Fun(l, r) => contains(sub, s, l) || contains(sub, s, r),
-            }
-        }
-
-        let mut subs = RefSub::default();
-        let mut stack = vec![(lhs, rhs)];
+        while let Some((l, r)) = stack.pop() {
+            let l = self.resolve(&news, l);
+            let r = self.resolve(&news, r);
 
-        while let Some(pair) = stack.pop() {
-            match pair {
+            match (l, r) {
                 (Var(x), Var(y)) if x == y => continue,
-                (t, Var(v)) | (Var(v), t) => {
-                    if contains(&subs, *v, t) {
+                (t, Var(v)) | (Var(v), t) if is_flexible(v) => {
+                    if self.occurs(&news, v, &t) {
                         return None;
                     }
 
-                    if let Some(expected) = self.dict.get(v) {
-                        stack.push((t, expected));
-                    }
-
-                    let Some(expected) = subs.get(v) else {
-                        subs.insert(*v, t);
-                        continue;
-                    };
-
-                    stack.push((t, expected));
+                    news.insert(v, t);
                 }
-                (Fun(lx, ly), Fun(rx, ry)) => {
-                    stack.push((lx, rx));
-                    stack.push((ly, ry));
+                (Fun(lx, ly), Fun(rx, ry))
+                | (Prod(lx, ly), Prod(rx, ry))
+                | (Sum(lx, ly), Sum(rx, ry)) => {
+                    stack.push(((*lx).clone(), (*rx).clone()));
+                    stack.push(((*ly).clone(), (*ry).clone()));
                 }
+                (List(l), List(r)) => stack.push(((*l).clone(), (*r).clone())),
+                _ => return None,
             }
         }
 
-        let news: Vec<_> = subs
-            .into_iter()
-            .map(|(id, mono)| (id, mono.clone()))
-            .collect();
-
-        for (id, mono) in news {
-            self.dict.insert(id, mono.clone());
-        }
+        self.dict.extend(news);
 
         let mut ty = lhs.clone();
         self.apply(&mut ty);
         Some(ty)
     }
 
+    // Replaces the quantified variables of a type scheme with fresh ones
+    pub fn instantiate(params: &[Identifier], ty: &Type) -> Type {
+        let sub = Self {
+            dict: params.iter().map(|p| (*p, Type::fresh())).collect(),
+        };
+
+        let mut ty = ty.clone();
+        sub.apply(&mut ty);
+        ty
+    }
+
     pub fn apply(&self, ty: &mut Type) {
         use Type::*;
         match ty {
@@ -78,10 +68,117 @@ impl TypeSub {
                     self.apply(ty);
                 }
             }
-            Fun(l, r) => {
-                self.apply(Rc::make_mut(l));
-                self.apply(Rc::make_mut(r));
+            Fun(l, r) | Prod(l, r) | Sum(l, r) => {
+                self.apply(Arc::make_mut(l));
+                self.apply(Arc::make_mut(r));
+            }
+            List(t) => self.apply(Arc::make_mut(t)),
+        }
+    }
+
+    // Follows substitutions of a variable until reaching a non-variable (or a free variable)
+    fn resolve(&self, news: &HashMap<Identifier, Type>, mut ty: Type) -> Type {
+        while let Type::Var(v) = ty {
+            match news.get(&v).or_else(|| self.dict.get(&v)) {
+                Some(next) => ty = next.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    fn occurs(&self, news: &HashMap<Identifier, Type>, v: Identifier, ty: &Type) -> bool {
+        use Type::*;
+        match self.resolve(news, ty.clone()) {
+            Var(w) => v == w,
+            Fun(l, r) | Prod(l, r) | Sum(l, r) => {
+                self.occurs(news, v, &l) || self.occurs(news, v, &r)
             }
+            List(t) => self.occurs(news, v, &t),
         }
     }
 }
+
+fn is_flexible(v: Identifier) -> bool {
+    matches!(v, Identifier::Uuid(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_variables_are_rigid() {
+        let mut sub = TypeSub::default();
+
+        assert_eq!(sub.unify(&ty!(N), &ty!(N)), Some(ty!(N)));
+        assert_eq!(sub.unify(&ty!(N), &ty!(Bool)), None);
+        assert_eq!(sub.unify(&ty!(a), &ty!(N)), None);
+        assert_eq!(sub.unify(&ty!(N => a), &ty!(N => b)), None);
+    }
+
+    #[test]
+    fn fresh_variables_are_substituted() {
+        let (a, b) = (Type::fresh(), Type::fresh());
+        let mut sub = TypeSub::default();
+
+        let unified = sub.unify(&ty!([a.clone()] => N), &ty!(Bool => [b.clone()]));
+        assert_eq!(unified, Some(ty!(Bool => N)));
+
+        let (mut a, mut b) = (a, b);
+        sub.apply(&mut a);
+        sub.apply(&mut b);
+        assert_eq!((a, b), (ty!(Bool), ty!(N)));
+    }
+
+    #[test]
+    fn occurs_check() {
+        let a = Type::fresh();
+        let mut sub = TypeSub::default();
+
+        assert_eq!(sub.unify(&a, &ty!([a.clone()] => N)), None);
+        assert_eq!(sub.unify(&ty!(List[a.clone()]), &a), None);
+    }
+
+    #[test]
+    fn failure_leaves_substitution_unchanged() {
+        let (a, b) = (Type::fresh(), Type::fresh());
+        let mut sub = TypeSub::default();
+
+        assert!(sub.unify(&a, &ty!(N)).is_some());
+
+        // `b` would be bound to `Bool` before `a` fails to unify with it
+        let lhs = ty!(([a.clone()], [b.clone()]));
+        assert_eq!(sub.unify(&lhs, &ty!((Bool, Bool))), None);
+
+        let mut unbound = b.clone();
+        sub.apply(&mut unbound);
+        assert_eq!(unbound, b);
+
+        let mut bound = a;
+        sub.apply(&mut bound);
+        assert_eq!(bound, ty!(N));
+
+        assert_eq!(sub.unify(&b, &ty!(N)), Some(ty!(N)));
+    }
+
+    #[test]
+    fn data_types_unify() {
+        let (a, b) = (Type::fresh(), Type::fresh());
+        let mut sub = TypeSub::default();
+
+        let lhs = ty!(([a.clone()], (List[b.clone()])));
+        let unified = sub.unify(&lhs, &ty!((N, (List(N | Bool)))));
+        assert_eq!(unified, Some(ty!((N, (List(N | Bool))))));
+
+        let mut b = b;
+        sub.apply(&mut b);
+        assert_eq!(b, ty!((N | Bool)));
+
+        let mut sub = TypeSub::default();
+        assert_eq!(sub.unify(&ty!((N, Bool)), &ty!((N | Bool))), None);
+        assert_eq!(sub.unify(&ty!((N, Bool)), &ty!(N => Bool)), None);
+        assert_eq!(sub.unify(&ty!(List N), &ty!(List Bool)), None);
+        assert_eq!(sub.unify(&ty!((N | Bool)), &ty!((Bool | N))), None);
+    }
+}